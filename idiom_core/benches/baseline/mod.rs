@@ -0,0 +1,921 @@
+/* The lexer as it was before it scanned bytes and borrowed its text: it walks a
+   Peekable<Chars> and copies every identifier, number and string into a String. Kept
+   only to measure the rewrite against, trimmed to what lexing needs */
+
+use idiom_core::*;
+
+#[derive(Debug, Clone)]
+pub struct Lexer<'a> {
+    // Readable positions
+    pub cur_line: usize,
+    pub cur_col: usize,
+
+    // Raw Index position
+    pub codepoint_offset: usize,
+    pub byte_offset: usize,
+
+    // Numeric Values
+    pub b64: bool,
+    pub seen_dot: bool,
+    pub seen_exp: bool,
+    pub radix: u32,
+
+    source: &'a str,
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    config: LexerConfig,
+    delimiters: Vec<OpenDelimiter>,
+    dash_mode: DashMode,
+
+    // Error recovery, problems are collected instead of returned
+    recovering: bool,
+    diagnostics: Vec<LexerError>,
+
+    // Keep whitespace as trivia on the tokens instead of skipping it
+    lossless: bool,
+
+    // Where the token currently being lexed began
+    token_start: Position,
+
+    // Whether the last token produced could end an expression
+    prev_operand: bool,
+
+    // Set once iteration has handed out EOF
+    finished: bool,
+
+    // Indentation mode, the open indentation levels and tokens owed before the next real one
+    indents: Vec<String>,
+    indent_char: Option<char>,
+    layout: std::collections::VecDeque<TokenType<'static>>,
+    held: Option<Vec<Trivia<'static>>>,
+    last_line: usize,
+    terminated: bool,
+}
+
+macro_rules! ingest {
+    ($self:ident, $($inner:tt),*) => {
+        if let Some(c) = $self.chars.peek() {
+            if ingest!(impl c, $($inner),*) {
+                let temp = *c;
+                $self.consume_space();
+                Some(temp)
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    };
+
+    (impl , ) => (false);
+    (impl $c:ident, $item:tt) => (*$c == $item);
+    (impl $c:ident, $item:tt, $($rest:tt), +) => (ingest!(impl $c, $item) || ingest!(impl $c, $($rest),+));
+}
+
+
+impl<'a> Lexer<'a> {
+    pub fn new(chars: &'a str) -> Lexer<'a> {
+        Lexer::with_config(chars, LexerConfig::default())
+    }
+
+    pub fn with_config(chars: &'a str, config: LexerConfig) -> Lexer<'a> {
+        Lexer {
+            cur_col: 1,
+            cur_line: 1,
+            codepoint_offset: 0,
+            byte_offset: 0,
+            radix: 10,
+            b64: false,
+            seen_dot: false,
+            seen_exp: false,
+
+            source: chars,
+            chars: chars.chars().peekable(),
+            config,
+            delimiters: Vec::new(),
+            dash_mode: DashMode::Auto,
+            recovering: false,
+            diagnostics: Vec::new(),
+            lossless: false,
+            token_start: Position { offset: 0, line: 1, col: 1 },
+            prev_operand: false,
+            finished: false,
+            indents: Vec::new(),
+            indent_char: None,
+            layout: std::collections::VecDeque::new(),
+            held: None,
+            last_line: 0,
+            terminated: true,
+        }
+    }
+
+    // Not `position`, which Iterator already claims
+    pub fn location(&self) -> Position {
+        Position { offset: self.byte_offset, line: self.cur_line, col: self.cur_col }
+    }
+
+    // Span from the start of the current token up to the cursor
+    fn span(&self) -> Span {
+        Span { start: self.token_start, end: self.location() }
+    }
+
+    // Source text exactly as written, for tools that re-emit it
+    pub fn slice(&self, span: Span) -> &'a str {
+        self.source.get(span.start.offset..span.end.offset).unwrap_or_default()
+    }
+
+    // Decodes the escape after a '\\', `start` being where the backslash sits
+    fn parse_escape(&mut self, start: Position) -> Result<char, LexerError> {
+        let mut sequence = "\\".to_string();
+        let escaped = match self.consume_space() {
+            Some(c) => { sequence.push(c); c },
+            None => '\0',
+        };
+
+        let decoded = match escaped {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            '0' => Some('\0'),
+            '\\' | '"' | '\'' => Some(escaped),
+            'u' if self.chars.peek() == Some(&'{') => {
+                while let Some(c) = self.chars.peek() {
+                    if !(c.is_ascii_hexdigit() || *c == '{' || *c == '}') || sequence.ends_with('}') {
+                        break;
+                    }
+                    sequence.push(*c);
+                    self.consume_space();
+                }
+
+                sequence.strip_prefix("\\u{").and_then(|s| s.strip_suffix('}'))
+                    .filter(|digits| (1..=6).contains(&digits.len()))
+                    .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                    .and_then(char::from_u32)
+            },
+            _ => None,
+        };
+
+        decoded.ok_or(LexerError::InvalidEscapeSequence { sequence, span: Span { start, end: self.location() } })
+    }
+
+    fn parse_string(&mut self) -> Result<TokenType<'static>, LexerError> {
+        let mut buf = String::new();
+        let mut invalid = None;
+
+        loop {
+            let at = self.location();
+            match self.consume_space() {
+                // a bad escape is reported once the literal is closed, so lexing resumes after it
+                Some('"') => break match invalid {
+                    Some(err) => Err(err),
+                    None => Ok(TokenType::String(buf.into())),
+                },
+                Some('\\') => match self.parse_escape(at) {
+                    Ok(c) => buf.push(c),
+                    Err(err) => { invalid.get_or_insert(err); },
+                },
+                Some(c) => buf.push(c),
+                None => break Err(LexerError::StringLiteralCollapse{expected: "\"".to_string(), received: TokenType::EOF, span: self.span()})
+            }
+        }
+    }
+
+    fn parse_identifier(&mut self, start: char) -> TokenType<'static> {
+        let mut raw = start.to_string();
+
+        while let Some(c) = self.chars.peek() {
+            if !(c.is_alphanumeric() || *c == '_') {
+                break;
+            }
+            raw.push(*c);
+            self.consume_space();
+        }
+
+        match KeywordsKind::lookup(&raw) {
+            // booleans are one bit binary numerics, the source spelling stays available through slice()
+            Some(KeywordsKind::True) => TokenType::Numeric{ raw: "1".to_string().into(), base: NumericBase::Binary, kind: NumericKind::Bool, suffix: None },
+            Some(KeywordsKind::False) => TokenType::Numeric{ raw: "0".to_string().into(), base: NumericBase::Binary, kind: NumericKind::Bool, suffix: None },
+            Some(keyword) => TokenType::Keywords(keyword),
+            None => TokenType::Identifiers(raw.into()),
+        }
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.chars.clone().nth(n)
+    }
+
+    fn parse_character(&mut self) -> Result<TokenType<'static>, LexerError> {
+        let mut chars = Vec::new();
+        let mut invalid = None;
+
+        loop {
+            let at = self.location();
+            match self.chars.peek() {
+                // character literals never span lines
+                None | Some('\n') => return Err(LexerError::UnterminatedCharacterLiteral{ raw: self.slice(self.span()).to_string(), span: self.span() }),
+                Some('\'') => { self.consume_space(); break; },
+                Some('\\') => {
+                    self.consume_space();
+                    match self.parse_escape(at) {
+                        Ok(c) => chars.push(c),
+                        Err(err) => { invalid.get_or_insert(err); },
+                    }
+                },
+                Some(c) => { chars.push(*c); self.consume_space(); },
+            }
+        }
+
+        if let Some(err) = invalid {
+            return Err(err);
+        }
+
+        match chars.as_slice() {
+            [c] => Ok(TokenType::Character(*c)),
+            [] => Err(LexerError::EmptyCharacterLiteral{ span: self.span() }),
+            _ => Err(LexerError::MultiCharacterLiteral{ raw: self.slice(self.span()).to_string(), span: self.span() }),
+        }
+    }
+
+    fn map_num_to_base(n: &str) -> Option<NumericBase> {
+        if !n.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        n.parse::<u32>().ok().and_then(NumericBase::from_radix)
+    }
+
+    fn digest_digit(&mut self, empty: bool) -> Result<String, LexerError> {
+        let mut raw = String::new();
+        loop{
+            match self.chars.peek(){
+                // '.' is handled by parse_numbers, 'b' and 'e' introduce bases and exponents of decimals
+                // Base64 runs to a boundary so stray characters are reported rather than lexed apart
+                Some(c) if self.b64 && ends_base64(*c) => {},
+                Some(c) if self.b64 => {
+                    raw.push(*c);
+                    self.consume_space();
+                    continue;
+                },
+                Some(c) if c.is_whitespace() || *c == '.' => {},
+                Some(c) if (*c == 'b' || *c == 'B' || *c == 'e' || *c == 'E') && self.radix == 10 => {},
+                Some(c) if c.is_alphanumeric() || *c == '_' => {
+                    raw.push(*c); 
+                    self.consume_space(); 
+                    continue;
+                },
+                // anything else (operators, delimiters) ends the literal
+                _ => {},
+            }
+
+            break match self.chars.peek() {
+                _ if empty || !raw.is_empty() => Ok(raw),
+                None => Err(LexerError::NumericLiteralCollapse{ 
+                    received: TokenType::EOF,
+                    expected: Box::new(Numeric {
+                        raw: "<int>".to_string(),
+                        base: NumericBase::Any,
+                        kind: NumericKind::Any,
+                        suffix: None,
+                    }),
+                    span: self.span(),
+                }),
+                Some(c) => Err(LexerError::UnknownNumericLiteral{ raw, received: *c, span: self.span() }),
+            }
+        }
+    }
+
+    fn digit_digest(&mut self, raw: &str) -> Result<(), LexerError> {
+        let mut local_dot = false;
+        let span = self.span();
+
+        let digits = raw.chars().collect::<Vec<char>>();
+
+        for (i, &c) in digits.iter().enumerate() {
+            if self.b64 {
+                if base64_digit(c).is_none() {
+                    return Err(LexerError::InvalidBase64Digit{ raw: raw.to_string(), invalid: c.to_string(), span });
+                }
+                continue;
+            }
+
+            // separators only ever sit between two digits, '1_000', '2b1010_1100'
+            if c == '_' {
+                let between_digits = i > 0 && digits[i - 1].is_digit(self.radix) && digits.get(i + 1).is_some_and(|n| n.is_digit(self.radix));
+                if !between_digits {
+                    return Err(LexerError::MisplacedDigitSeparator{ raw: raw.to_string(), span });
+                }
+                continue;
+            }
+
+            if (c == 'e' || c == 'E' || c == '+' || c == '-') && self.seen_exp { } 
+            else if !c.is_digit(self.radix) && c != '.' {
+                match self.radix {
+                    2 => return Err(LexerError::InvalidBinaryValue{ raw: raw.to_string(), invalid: c.to_string(), span }),
+                    8 => return Err(LexerError::InvalidOctalValue{ raw: raw.to_string(), invalid: c.to_string(), span }),
+                    10 => return Err(LexerError::InvalidDecimalValue{ raw: raw.to_string(), invalid: c.to_string(), span }),
+                    16 =>  return Err(LexerError::InvalidHexadecimalValue{ raw: raw.to_string(), invalid: c.to_string(), span }),
+                    _ => return Err(LexerError::InvalidNumericLiteral{ base: NumericBase::from_radix(self.radix).unwrap_or(NumericBase::Any), raw: raw.to_string(), received: c.to_string(), span })
+                }
+            }
+
+            if c == '.' && !local_dot { local_dot = true; } 
+            else if c == '.' && local_dot { return Err(LexerError::InvalidFractionalValue{ raw: raw.to_string(), received: c.to_string(), span }) }
+        }
+        Ok(())
+    }
+
+    fn parse_numbers(&mut self, start: char) -> Result<TokenType<'static>, LexerError> {
+        self.radix = 10;
+        self.b64 = false;
+        self.seen_dot = false;
+        self.seen_exp = false;
+        let mut raw = start.to_string();
+        let mut kind = NumericKind::Whole;
+        let mut base = NumericBase::Decimal;
+
+        // parse fractions written without a leading zero, '.798'
+        if start == '.' {
+            self.seen_dot = true;
+            raw += &self.digest_digit(false)?;
+            kind = NumericKind::Fractional;
+        }
+
+        // parse leading numerical values
+        if start.is_digit(self.radix) || start == '.' {
+            if start != '.' {
+                raw += &self.digest_digit(true)?;
+            }
+
+            // parse decimal values, a dot without digits behind it terminates the statement instead
+            if !self.seen_dot && self.chars.peek() == Some(&'.') && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) {
+                raw.push('.');
+                self.consume_space();
+                self.seen_dot = true;
+                raw += &self.digest_digit(false)?;
+                kind = NumericKind::Fractional;
+            }
+
+            // a fraction running into another dot is ambiguous ('.2.', '1.2.3')
+            if self.seen_dot && self.chars.peek() == Some(&'.') {
+                while let Some(c) = self.chars.peek() {
+                    if !(c.is_alphanumeric() || *c == '.') {
+                        break;
+                    }
+                    raw.push(*c);
+                    self.consume_space();
+                }
+                return Err(LexerError::InvalidFractionalValue{ raw, received: ".".to_string(), span: self.span() });
+            }
+
+            // parse exponential values
+            if let Some(c) = ingest!(self, 'e', 'E') {
+                kind = NumericKind::Exponential;
+                base = NumericBase::Decimal;
+                self.seen_exp = true;
+                raw.push(c);
+                
+                if let Some(c) = ingest!(self, '+', '-') {
+                    raw.push(c);
+                }
+
+                raw += &self.digest_digit(false)?;
+            }
+            
+            // parse variable base values
+            if kind == NumericKind::Whole && ingest!(self, 'b', 'B').is_some() {  // explicit base declaration
+                let raw_base = raw.clone();
+                raw.clear();
+
+                base = match Lexer::<'a>::map_num_to_base(&raw_base) {
+                    Some(base) => base,
+                    None => return Err(LexerError::InvalidNumericBase { base: raw_base, span: self.span() }),
+                };
+
+                if base == NumericBase::Base64 { 
+                    self.b64 = true; 
+                    self.radix = 10; 
+                } else {       
+                    self.radix = base.radix(); 
+                }
+
+                raw += &self.digest_digit(false)?;
+            }
+        } else {
+            return Err(LexerError::InvalidNumericLiteral {
+                base,
+                raw,
+                received: start.to_string(),
+                span: self.span(),
+            });
+        }
+        let suffix = self.split_suffix(&mut raw);
+        let _ = &self.digit_digest(&raw)?;
+
+        if let Some(suffix) = suffix {
+            let token = Token::new(TokenType::Numeric{ raw: raw.clone().into(), base: base.clone(), kind: kind.clone(), suffix: None }, self.span());
+            if !evaluate(&token).is_ok_and(|evaluated| suffix.can_represent(&evaluated.value)) {
+                return Err(LexerError::UnrepresentableLiteral{ raw: self.slice(self.span()).to_string(), suffix, span: self.span() });
+            }
+        }
+
+        Ok(TokenType::Numeric{ raw: raw.into(), base, kind, suffix })
+    }
+
+    // Takes a trailing 'u8', 'i32', 'f64'.. off the digits. A suffix starting with a
+    // digit of the radix ('f' in hexadecimal) is read as digits instead
+    fn split_suffix(&self, raw: &mut String) -> Option<NumericSuffix> {
+        if self.b64 {
+            return None;
+        }
+
+        let (text, suffix) = NUMERIC_SUFFIXES.iter().find(|(text, _)| {
+            raw.len() > text.len() && raw.ends_with(text) && !text.starts_with(|c: char| c.is_digit(self.radix))
+        })?;
+        raw.truncate(raw.len() - text.len());
+        Some(*suffix)
+    }
+    
+    fn open_delimiters(&mut self, c: &char) -> i32 {
+        let depth = self.depth(*c);
+        self.delimiters.push(OpenDelimiter { raw: *c, span: self.span() });
+        depth
+    }
+
+    // Only the innermost delimiter may close, '( [ ) ]' leaves the stack as it was
+    fn close_delimiters(&mut self, c: &char) -> Result<i32, LexerError> {
+        let opener = match self.config.closed_by(*c) {
+            Some(pair) => pair.open,
+            None => return Err(LexerError::UnknownDelimiter{ symbol: *c, span: self.span() }),
+        };
+
+        let beneath = self.delimiters.len().checked_sub(2).map(|i| self.delimiters[i].raw);
+        if self.config.implicit_parameter_close && self.innermost_role() == Some(DelimiterRole::Parameters)
+            && self.innermost() != Some(opener) && beneath == Some(opener) {
+            self.delimiters.pop();
+        }
+
+        match self.delimiters.last() {
+            Some(open) if open.raw == opener => {
+                self.delimiters.pop();
+                Ok(self.depth(opener))
+            },
+            Some(open) if self.depth(opener) > 0 => Err(LexerError::CrossedDelimiters{
+                symbol: *c,
+                expected: self.closer_of(open.raw),
+                open: open.span,
+                span: self.span(),
+            }),
+            _ => Err(LexerError::MisMatchedDelimiters{symbol: *c, requires: opener, span: self.span()}),
+        }
+    }
+
+    fn depth(&self, c: char) -> i32 {
+        self.delimiters.iter().filter(|open| open.raw == c).count() as i32
+    }
+
+    fn innermost(&self) -> Option<char> {
+        self.delimiters.last().map(|open| open.raw)
+    }
+
+    fn innermost_role(&self) -> Option<DelimiterRole> {
+        self.innermost().and_then(|c| self.config.opened_by(c)).map(|pair| pair.role)
+    }
+
+    // Only configured openers are ever pushed, so the fallback is never reached
+    fn closer_of(&self, opener: char) -> char {
+        self.config.opened_by(opener).map_or(opener, |pair| pair.close)
+    }
+
+    fn unclosed_error(&self, open: &OpenDelimiter) -> LexerError {
+        LexerError::UnclosedDelimiter{ symbol: open.raw, requires: self.closer_of(open.raw), span: open.span }
+    }
+
+    // Anything left open is reported once the input runs out: collected in source
+    // order when recovering, otherwise one error per call, innermost first
+    fn end_of_input(&mut self) -> Result<TokenType<'static>, LexerError> {
+        if self.recovering {
+            for open in std::mem::take(&mut self.delimiters) {
+                let error = self.unclosed_error(&open);
+                self.diagnostics.push(error);
+            }
+        }
+
+        match self.delimiters.pop() {
+            Some(open) => Err(self.unclosed_error(&open)),
+            None => Ok(TokenType::EOF),
+        }
+    }
+
+    // Longest operator starting with `start` that the upcoming input spells out
+    fn longest_operator(&self, start: char) -> Option<&'static str> {
+        OPERATORS.iter().copied().find(|op| {
+            let mut expected = op.chars();
+            let mut upcoming = self.chars.clone();
+            expected.next() == Some(start) && expected.all(|e| upcoming.next() == Some(e))
+        })
+    }
+
+    // Whether an operand starts after the cursor, on the same line
+    fn operand_follows(&self) -> bool {
+        match self.chars.clone().find(|c| *c != ' ' && *c != '\t') {
+            Some(c) => c.is_alphanumeric() || c == '_' || c == '(' || c == '"' || c == '\'',
+            None => false,
+        }
+    }
+
+    // In Auto mode a '-' closes the innermost parameter list, unless it sits
+    // between two operands and so reads as a subtraction
+    fn dash_closes_parameters(&self, pair: &DelimiterPair) -> bool {
+        match self.dash_mode {
+            DashMode::Delimiter => true,
+            DashMode::Operator => false,
+            DashMode::Auto => self.innermost() == Some(pair.open) && !(self.prev_operand && self.operand_follows()),
+        }
+    }
+
+    fn operator_closes(&self, pair: &DelimiterPair) -> bool {
+        match pair.role {
+            DelimiterRole::Parameters => self.dash_closes_parameters(pair),
+            _ => self.innermost() == Some(pair.open),
+        }
+    }
+
+    // Operators that double as delimiters ('-', '<' and '>' by default) are settled
+    // by the shape of the operator and the surrounding context
+    fn parse_operator(&mut self, start: char) -> Result<TokenType<'static>, LexerError> {
+        let op = match self.longest_operator(start) {
+            Some(op) => op,
+            None => return Err(LexerError::UnknownPokemon{ unknowns: start.to_string(), span: self.span() }),
+        };
+
+        for _ in 1..op.chars().count() {
+            self.consume_space();
+        }
+
+        if op.len() == start.len_utf8() {
+            if let Some(pair) = self.config.closed_by(start).copied() {
+                if self.operator_closes(&pair) {
+                    return Ok(TokenType::Delimiters{ raw: start, kind: DelimitersKind::Closing(self.close_delimiters(&start)?) });
+                }
+            }
+
+            if self.config.opened_by(start).is_some() && !self.prev_operand {
+                return Ok(TokenType::Delimiters{ raw: start, kind: DelimitersKind::Opening(self.open_delimiters(&start)) });
+            }
+        }
+
+        // the leading dash of '-->' closes the open parameter list before sending the result on
+        if op == "-->" && self.innermost_role() == Some(DelimiterRole::Parameters) {
+            if let Some(pair) = self.config.role(DelimiterRole::Parameters).filter(|pair| pair.close == '-').copied() {
+                self.close_delimiters(&pair.close)?;
+            }
+        }
+
+        Ok(TokenType::Operators(op.to_string().into()))
+    }
+
+    // A character may open one pair and close another, or both open and close the
+    // same pair, in which case closing the innermost delimiter wins
+    fn parse_delimiter(&mut self, c: char) -> Result<TokenType<'static>, LexerError> {
+        let closing = self.config.closed_by(c).map(|pair| pair.open);
+        let opening = self.config.opened_by(c).is_some();
+
+        if closing.is_some() && (!opening || closing == self.innermost()) {
+            Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Closing(self.close_delimiters(&c)?) })
+        } else {
+            Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Opening(self.open_delimiters(&c)) })
+        }
+    }
+
+    pub fn transform_content(&mut self, c: char) -> Result<TokenType<'static>, LexerError> {
+        let operator = OPERATORS.iter().any(|op| op.starts_with(c));
+
+        match c {
+            // Delimiters, those that are also operators are left to parse_operator
+            c if !operator && self.config.is_separator(c) => Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Seperator }),
+            c if !operator && self.config.is_delimiter(c) => self.parse_delimiter(c),
+            c if DELIMITER_LIKE.contains(&c) => Err(LexerError::UnknownDelimiter{ symbol: c, span: self.span() }),
+
+            // Numbers
+            '0' ..= '9' => self.parse_numbers(c),
+            '.' if self.chars.peek().is_some_and(|c| c.is_ascii_digit()) => self.parse_numbers(c),
+
+            // Statement Terminator
+            '.' => Ok(TokenType::Terminator),
+            
+            // Strings
+            '"' => self.parse_string(),
+            '\'' => self.parse_character(),
+
+            // Doc comments, plain ones are trivia and never get this far
+            '/' if self.upcoming("//") && self.peek_nth(2) != Some('/') => Ok(self.parse_doc_comment()),
+
+            // Operators
+            _ if operator => self.parse_operator(c),
+
+            // Indentifiers
+            c if c.is_alphabetic() || c == '_' => Ok(self.parse_identifier(c)),
+
+            _ => Err(LexerError::UnknownPokemon{ unknowns: c.to_string(), span: self.span() })
+        }
+    }
+    
+    fn consume_space(&mut self) -> Option<char> {
+        match self.chars.next() {
+            Some(c) => {
+                self.cur_col += 1;
+
+                if c == '\n' {
+                    self.cur_line += 1;
+                    self.cur_col = 1;
+                }
+
+                self.codepoint_offset += 1;
+                self.byte_offset += c.len_utf8();
+
+                Some(c)
+            }
+            None => None
+        }
+    }
+
+    // An error that stopped partway through a word ('48b' of '48b1239419') skips
+    // the rest of it, so the remainder isn't lexed as tokens of its own
+    fn resync(&mut self) {
+        let in_word = |c: char| c.is_alphanumeric() || c == '_';
+
+        if !self.slice(self.span()).chars().next_back().is_some_and(in_word) {
+            return;
+        }
+        while let Some(c) = self.chars.peek() {
+            if !in_word(*c) {
+                break;
+            }
+            self.consume_space();
+        }
+    }
+
+
+    fn at_newline(&self) -> bool {
+        match self.chars.clone().next() {
+            Some('\n') => true,
+            Some('\r') => self.peek_nth(1) == Some('\n'),
+            _ => false,
+        }
+    }
+
+    fn upcoming(&self, text: &str) -> bool {
+        let mut upcoming = self.chars.clone();
+        text.chars().all(|c| upcoming.next() == Some(c))
+    }
+
+    // '///' documents, while '//' and four or more slashes are plain comments
+    fn at_doc_comment(&self) -> bool {
+        self.upcoming("///") && self.peek_nth(3) != Some('/')
+    }
+
+    fn skip_line(&mut self) {
+        while self.chars.peek().is_some() && !self.at_newline() {
+            self.consume_space();
+        }
+    }
+
+    // Block comments nest, so '/* a /* b */ c */' is one comment
+    fn skip_block_comment(&mut self, start: Position) -> Result<(), LexerError> {
+        let mut depth = 0;
+        loop {
+            if self.upcoming("/*") {
+                depth += 1;
+            } else if self.upcoming("*/") {
+                depth -= 1;
+            } else if self.consume_space().is_some() {
+                continue;
+            } else {
+                return Err(LexerError::UnterminatedBlockComment{ span: Span { start, end: self.location() } });
+            }
+
+            self.consume_space();
+            self.consume_space();
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /* Whitespace and comments ahead of a token, or after one up to the end of its line.
+       Block comments after a token are left to lead the next one */
+    fn trivia(&mut self, trailing: bool) -> Result<Vec<Trivia<'static>>, LexerError> {
+        let mut trivia = Vec::new();
+        if trailing && !self.lossless {
+            return Ok(trivia);
+        }
+
+        loop {
+            let start = self.location();
+            let kind = match self.chars.peek().copied() {
+                _ if self.at_newline() => {
+                    if self.consume_space() == Some('\r') {
+                        self.consume_space();
+                    }
+                    TriviaKind::Newline
+                },
+                Some(c) if c.is_whitespace() => {
+                    while self.chars.peek().is_some_and(|c| c.is_whitespace()) && !self.at_newline() {
+                        self.consume_space();
+                    }
+                    TriviaKind::Whitespace
+                },
+                Some('/') if self.upcoming("//") && !self.at_doc_comment() => {
+                    self.skip_line();
+                    TriviaKind::LineComment
+                },
+                Some('/') if self.upcoming("/*") && !trailing => {
+                    if let Err(err) = self.skip_block_comment(start) {
+                        if !self.recovering {
+                            return Err(err);
+                        }
+                        self.diagnostics.push(err);
+                    }
+                    TriviaKind::BlockComment
+                },
+                _ => break,
+            };
+
+            if self.lossless {
+                let span = Span { start, end: self.location() };
+                trivia.push(Trivia { kind, text: self.slice(span).to_string().into(), span });
+            }
+
+            if trailing && kind == TriviaKind::Newline {
+                break;
+            }
+        }
+        Ok(trivia)
+    }
+
+    fn parse_doc_comment(&mut self) -> TokenType<'static> {
+        self.consume_space();
+        self.consume_space();
+
+        let start = self.location();
+        self.skip_line();
+        let text = self.slice(Span { start, end: self.location() });
+        TokenType::DocComment(text.strip_prefix(' ').unwrap_or(text).to_string().into())
+    }
+
+    fn line_start(&self) -> usize {
+        let before = self.source.get(..self.byte_offset).unwrap_or_default();
+        before.rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    // Indentation of the line the cursor is on
+    fn line_indent(&self) -> String {
+        let line = self.source.get(self.line_start()..).unwrap_or_default();
+        line.chars().take_while(|c| *c == ' ' || *c == '\t').collect()
+    }
+
+    fn inconsistent(&self, found: &str) -> LexerError {
+        let start = Position { offset: self.line_start(), line: self.cur_line, col: 1 };
+        let end = Position { offset: start.offset + found.len(), line: self.cur_line, col: 1 + found.chars().count() };
+
+        LexerError::InconsistentIndentation {
+            expected: self.indents.last().cloned().unwrap_or_default(),
+            found: found.to_string(),
+            span: Span { start, end },
+        }
+    }
+
+    /* Owed once the cursor has moved onto a new line: a Terminator for the statement left
+       behind, then an Indent for a deeper line or a Dedent per level given up. Lines inside
+       brackets continue the one they started on */
+    fn line_break(&mut self) -> Result<(), LexerError> {
+        let at_end = self.chars.peek().is_none();
+        let continued = self.delimiters.last()
+            .and_then(|open| self.config.opened_by(open.raw))
+            .is_some_and(|pair| pair.role != DelimiterRole::FunctionBody);
+
+        if continued || self.last_line == 0 || (!at_end && self.cur_line == self.last_line) {
+            return Ok(());
+        }
+        self.last_line = self.cur_line;
+
+        let indent = if at_end { String::new() } else { self.line_indent() };
+        let top = self.indents.last().cloned().unwrap_or_default();
+
+        let mixed = match (self.indent_char, indent.chars().next()) {
+            (_, None) => false,
+            (Some(c), _) => indent.chars().any(|i| i != c),
+            (None, Some(first)) => indent.chars().any(|i| i != first),
+        };
+        if mixed {
+            return Err(self.inconsistent(&indent));
+        }
+        self.indent_char = self.indent_char.or(indent.chars().next());
+
+        if indent.len() > top.len() {
+            if !indent.starts_with(&top) {
+                return Err(self.inconsistent(&indent));
+            }
+            self.indents.push(indent);
+            self.layout.push_back(TokenType::Indent);
+            return Ok(());
+        }
+
+        if !self.terminated {
+            self.layout.push_back(TokenType::Terminator);
+        }
+        while self.indents.last().is_some_and(|level| level.len() > indent.len()) {
+            self.indents.pop();
+            self.layout.push_back(TokenType::Dedent);
+        }
+
+        if self.indents.last().map_or("", String::as_str) != indent {
+            let error = self.inconsistent(&indent);
+            self.indents.push(indent);
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // Synthetic tokens take no room in the source, so they sit where the next real token starts
+    fn layout_token(&mut self) -> Result<Option<Token<'static>>, LexerError> {
+        if self.held.is_none() {
+            let leading = self.trivia(false)?;
+            self.held = Some(leading);
+
+            if let Err(err) = self.line_break() {
+                if !self.recovering {
+                    return Err(err);
+                }
+                self.diagnostics.push(err);
+            }
+        }
+
+        let kind = match self.layout.pop_front() {
+            Some(kind) => kind,
+            None => return Ok(None),
+        };
+        self.terminated = true;
+        self.prev_operand = false;
+
+        let at = self.location();
+        Ok(Some(Token::new(kind, Span { start: at, end: at })))
+    }
+
+    pub fn next_token(&mut self) -> Result<Token<'static>, LexerError> {
+        if self.config.indentation {
+            if let Some(token) = self.layout_token()? {
+                return Ok(token);
+            }
+        }
+
+        let leading = match self.held.take() {
+            Some(leading) => leading,
+            None => self.trivia(false)?,
+        };
+        self.token_start = self.location();
+
+        let kind = if let Some(c) = self.consume_space(){
+            self.transform_content(c)
+        } else {
+            self.end_of_input()
+        };
+
+        let kind = match kind {
+            Ok(kind) => kind,
+            Err(err) if self.recovering => {
+                self.resync();
+                self.diagnostics.push(err);
+                TokenType::Error(self.slice(self.span()).to_string().into())
+            },
+            Err(err) => return Err(err),
+        };
+
+        // Doc comments sit between items and leave the context as it was
+        if !matches!(kind, TokenType::DocComment(_)) {
+            self.prev_operand = matches!(kind,
+                TokenType::Identifiers(_) | TokenType::Numeric{..} | TokenType::String(_) | TokenType::Character(_) | TokenType::Error(_) |
+                TokenType::Keywords(KeywordsKind::Null) |
+                TokenType::Delimiters{ kind: DelimitersKind::Closing(_), .. });
+            self.terminated = kind == TokenType::Terminator;
+        }
+
+        let span = self.span();
+        self.last_line = span.end.line;
+        let trailing = if kind == TokenType::EOF { Vec::new() } else { self.trivia(true)? };
+
+        Ok(Token { kind, span, leading, trailing })
+    }
+}
+
+// Yields every token up to and including EOF, errors along the way included
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'static>, LexerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let token = self.next_token();
+        self.finished = matches!(token, Ok(Token { kind: TokenType::EOF, .. }));
+        Some(token)
+    }
+}
@@ -0,0 +1,62 @@
+/* Lexer throughput over a generated corpus, `cargo bench -p idiom_core -- <megabytes>`.
+   Tokens borrowing the source are measured next to the same tokens made to own their text,
+   next to ReadLexer reading them from a byte slice, and next to the char by char lexer
+   this one replaced */
+
+use std::time::{Duration, Instant};
+
+use idiom_core::*;
+
+mod baseline;
+
+const SEED: &str = include_str!("corpus/seed.idi");
+const RUNS: usize = 5;
+
+// The seed repeated up to the requested size
+fn corpus(megabytes: usize) -> String {
+    let size = megabytes * 1024 * 1024;
+    let mut corpus = String::with_capacity(size + SEED.len());
+    while corpus.len() < size {
+        corpus += SEED;
+    }
+    corpus
+}
+
+// Best of RUNS at lexing `corpus`, where `lex` returns how many tokens it got
+fn measure(name: &str, megabytes: usize, corpus: &str, lex: impl Fn(&str) -> usize) {
+    let mut best = Duration::MAX;
+    let mut tokens = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        tokens = lex(corpus);
+        best = best.min(start.elapsed());
+    }
+
+    let seconds = best.as_secs_f64();
+    println!("{}: lexed {} MB, {} tokens in {:.3}s (best of {})", name, megabytes, tokens, seconds, RUNS);
+    println!("{}: {:.1} MB/s, {:.2} M tokens/s", name, megabytes as f64 / seconds, tokens as f64 / seconds / 1e6);
+}
+
+fn main() {
+    let megabytes = std::env::args().skip(1)
+        .find_map(|arg| arg.parse::<usize>().ok())
+        .unwrap_or(16);
+    let corpus = corpus(megabytes);
+
+    measure("baseline", megabytes, &corpus, |corpus| {
+        baseline::Lexer::new(corpus).filter(|token| token.is_ok()).count()
+    });
+
+    measure("borrowed", megabytes, &corpus, |corpus| {
+        Lexer::new(corpus).filter(|token| token.is_ok()).count()
+    });
+
+    // every token copied out of the source, as they were before tokens borrowed their text
+    measure("owned", megabytes, &corpus, |corpus| {
+        Lexer::new(corpus).filter_map(|token| token.ok().map(Token::into_owned)).count()
+    });
+
+    measure("read", megabytes, &corpus, |corpus| {
+        ReadLexer::new(corpus.as_bytes()).filter(|token| token.is_ok()).count()
+    });
+}
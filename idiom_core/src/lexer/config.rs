@@ -0,0 +1,113 @@
+// Lexer settings that dialects and experiments may change without forking the lexer
+
+use crate::lexer::*;
+
+// What a pair of delimiters means to the language, independent of how it is spelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DelimiterRole {
+    FunctionBody,
+    Parameters,
+    Object,
+    Vector,
+    Array,
+    Join,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DelimiterPair {
+    pub open: char,
+    pub close: char,
+    pub role: DelimiterRole,
+}
+
+impl DelimiterPair {
+    pub const fn new(open: char, close: char, role: DelimiterRole) -> DelimiterPair {
+        DelimiterPair { open, close, role }
+    }
+}
+
+pub const DEFAULT_DELIMITERS: &[DelimiterPair] = &[
+    DelimiterPair::new('|', '~', DelimiterRole::FunctionBody),
+    DelimiterPair::new(',', '-', DelimiterRole::Parameters),
+    DelimiterPair::new('{', '}', DelimiterRole::Object),
+    DelimiterPair::new('<', '>', DelimiterRole::Vector),
+    DelimiterPair::new('[', ']', DelimiterRole::Array),
+    DelimiterPair::new('(', ')', DelimiterRole::Join),
+];
+
+// Characters that read as delimiters even when the table leaves them out
+pub const DELIMITER_LIKE: &[char] = &['|', '~', ',', '{', '}', '[', ']', '(', ')'];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexerConfig {
+    // Earlier pairs win when two of them share a character
+    pub delimiters: Vec<DelimiterPair>,
+
+    // Characters that only separate, like ',' between the arguments of a .etu call
+    pub separators: Vec<char>,
+
+    // Line breaks and indentation end statements and open or close blocks
+    pub indentation: bool,
+
+    // A parameter list may be left open for the bracket around it to close, as in '(f: a, b)'
+    pub implicit_parameter_close: bool,
+}
+
+impl Default for LexerConfig {
+    fn default() -> LexerConfig {
+        LexerConfig::new(DEFAULT_DELIMITERS.to_vec())
+    }
+}
+
+impl LexerConfig {
+    pub fn new(delimiters: Vec<DelimiterPair>) -> LexerConfig {
+        LexerConfig { delimiters, separators: Vec::new(), indentation: false, implicit_parameter_close: false }
+    }
+
+    pub fn with_indentation(mut self, indentation: bool) -> LexerConfig {
+        self.indentation = indentation;
+        self
+    }
+
+    pub fn with_separator(mut self, separator: char) -> LexerConfig {
+        self.separators.push(separator);
+        self
+    }
+
+    pub fn with_implicit_parameter_close(mut self, implicit: bool) -> LexerConfig {
+        self.implicit_parameter_close = implicit;
+        self
+    }
+
+    // Replaces the pair playing `role`, or adds one if the table has none
+    pub fn with_pair(mut self, open: char, close: char, role: DelimiterRole) -> LexerConfig {
+        self.delimiters.retain(|pair| pair.role != role);
+        self.delimiters.push(DelimiterPair::new(open, close, role));
+        self
+    }
+
+    pub fn without_role(mut self, role: DelimiterRole) -> LexerConfig {
+        self.delimiters.retain(|pair| pair.role != role);
+        self
+    }
+
+    pub fn opened_by(&self, c: char) -> Option<&DelimiterPair> {
+        self.delimiters.iter().find(|pair| pair.open == c)
+    }
+
+    pub fn closed_by(&self, c: char) -> Option<&DelimiterPair> {
+        self.delimiters.iter().find(|pair| pair.close == c)
+    }
+
+    pub fn role(&self, role: DelimiterRole) -> Option<&DelimiterPair> {
+        self.delimiters.iter().find(|pair| pair.role == role)
+    }
+
+    pub fn is_separator(&self, c: char) -> bool {
+        self.separators.contains(&c)
+    }
+
+    pub fn is_delimiter(&self, c: char) -> bool {
+        self.opened_by(c).is_some() || self.closed_by(c).is_some()
+    }
+}
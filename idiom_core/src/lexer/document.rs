@@ -0,0 +1,213 @@
+// Keeps a document lexed as it is edited, lexing again only around each change
+
+use std::ops::Range;
+
+use crate::lexer::*;
+
+/* How far an edit moved the text after it. Positions from `from` on, in the text before
+   the edit, move by the rest */
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct Shift {
+    from: usize,
+    bytes: isize,
+    pub(crate) lines: isize,
+    pub(crate) chars: isize,
+}
+
+impl Shift {
+    fn new(removed: &str, replacement: &str, from: usize) -> Shift {
+        let lines = |text: &str| text.matches('\n').count() as isize;
+        let chars = |text: &str| text.chars().count() as isize;
+        Shift {
+            from,
+            bytes: replacement.len() as isize - removed.len() as isize,
+            lines: lines(replacement) - lines(removed),
+            chars: chars(replacement) - chars(removed),
+        }
+    }
+
+    // Where text that was at `offset` is now, if it survived the edit
+    pub(crate) fn moved(&self, offset: usize) -> Option<usize> {
+        (offset >= self.from).then(|| offset.saturating_add_signed(self.bytes))
+    }
+
+    pub(crate) fn position(&self, at: &mut Position) {
+        if let Some(offset) = self.moved(at.offset) {
+            at.offset = offset;
+            at.line = at.line.saturating_add_signed(self.lines);
+        }
+    }
+
+    pub(crate) fn span(&self, span: &mut Span) {
+        self.position(&mut span.start);
+        self.position(&mut span.end);
+    }
+
+    fn token(&self, token: &mut Token) {
+        self.span(&mut token.span);
+        token.leading.iter_mut().chain(token.trailing.iter_mut()).for_each(|trivia| self.span(&mut trivia.span));
+    }
+
+    fn piece(&self, piece: &mut Piece) {
+        piece.state.shift(self);
+        for token in &mut piece.tokens {
+            match token {
+                Ok(token) => self.token(token),
+                Err(err) => err.shift(self),
+            }
+        }
+        piece.diagnostics.iter_mut().for_each(|err| err.shift(self));
+    }
+}
+
+/* What was lexed between two points the lexer can stop at, with the state it stopped in
+   before it. Any piece can be lexed again on its own from that state */
+#[derive(Debug, Clone)]
+struct Piece {
+    state: LexerState,
+    tokens: Vec<Result<Token<'static>, LexerError>>,
+    diagnostics: Vec<LexerError>,
+}
+
+/* The result matches lexing the whole text afresh, errors and recovery included. In
+   tokens the text is owned, as it is in ReadLexer, so edits don't have to invalidate it */
+pub struct LexedDocument {
+    text: String,
+    config: LexerConfig,
+    pieces: Vec<Piece>,
+}
+
+impl LexedDocument {
+    pub fn new(text: impl Into<String>) -> LexedDocument {
+        LexedDocument::with_config(text, LexerConfig::default())
+    }
+
+    pub fn with_config(text: impl Into<String>, config: LexerConfig) -> LexedDocument {
+        let state = Lexer::with_config("", config.clone()).snapshot();
+        let mut document = LexedDocument { text: text.into(), config, pieces: Vec::new() };
+        document.lex(state, Vec::new(), &Shift::default());
+        document
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn config(&self) -> &LexerConfig {
+        &self.config
+    }
+
+    // Settings change how everything lexes, so the whole text is lexed again
+    fn adjust(&mut self, change: impl FnOnce(&mut Lexer)) {
+        let mut lexer = Lexer::resume("", 0, self.config.clone(), self.pieces[0].state.clone());
+        change(&mut lexer);
+        self.pieces.clear();
+        self.lex(lexer.snapshot(), Vec::new(), &Shift::default());
+    }
+
+    pub fn set_recovery(&mut self, recovering: bool) {
+        self.adjust(|lexer| lexer.set_recovery(recovering));
+    }
+
+    pub fn set_lossless(&mut self, lossless: bool) {
+        self.adjust(|lexer| lexer.set_lossless(lossless));
+    }
+
+    // Every token up to and including EOF, as Lexer hands them out
+    pub fn tokens(&self) -> impl Iterator<Item = &Result<Token<'static>, LexerError>> {
+        self.pieces.iter().flat_map(|piece| &piece.tokens)
+    }
+
+    pub fn diagnostics(&self) -> impl Iterator<Item = &LexerError> {
+        self.pieces.iter().flat_map(|piece| &piece.diagnostics)
+    }
+
+    /* The states lexing can be picked up from, each with the index of the token lexed
+       next. A Lexer resumed from one over the text carries on exactly as this one did */
+    pub fn snapshots(&self) -> impl Iterator<Item = (usize, &LexerState)> {
+        self.pieces.iter().scan(0, |index, piece| {
+            let at = *index;
+            *index += piece.tokens.len();
+            Some((at, &piece.state))
+        })
+    }
+
+    /* Replaces `range` of the text and lexes again from the last state before the line the
+       edit starts on, as the lexer looks ahead no further than the end of a line, unless it
+       finds the end of the text first. Tokens that ran into the end, and any state captured
+       there, would have come out differently with more text after them, so lexing restarts
+       before them and never rejoins them. Otherwise, once it reaches a state past the edit
+       that the old tokens were lexed from, the rest of them are kept, moved along. Returns
+       the indices of the tokens lexed again, or None without changing anything when `range`
+       doesn't fall on character boundaries */
+    pub fn edit(&mut self, range: Range<usize>, replacement: &str) -> Option<Range<usize>> {
+        if range.start > range.end || !self.text.is_char_boundary(range.start) || !self.text.is_char_boundary(range.end) {
+            return None;
+        }
+
+        let shift = Shift::new(&self.text[range.clone()], replacement, range.end);
+        let line_start = self.text[..range.start].rfind('\n').map_or(0, |at| at + 1);
+        let end = self.text.len();
+        self.text.replace_range(range, replacement);
+
+        let first = self.pieces.iter()
+            .rposition(|piece| piece.state.position().offset <= line_start && piece.state.position().offset < end)
+            .unwrap_or(0);
+        let old = self.pieces.split_off(first);
+        let start = self.tokens().count();
+        let relexed = self.lex(old[0].state.clone(), old, &shift);
+        Some(start..start + relexed)
+    }
+
+    /* Lexes from `state` to the end of the text, unless it comes to a state one of `old`
+       was lexed from, once moved by `shift`, where that piece and the rest are kept instead.
+       Returns how many tokens were lexed */
+    fn lex(&mut self, state: LexerState, mut old: Vec<Piece>, shift: &Shift) -> usize {
+        let mut lexer = Lexer::resume(&self.text, 0, self.config.clone(), state);
+        let mut lexed = 0;
+        let mut next = 0;
+
+        loop {
+            let state = lexer.snapshot();
+            let at = state.position().offset;
+
+            // pieces behind the lexer, or lexed from text the edit replaced, can't be met again
+            let moved = |piece: &Piece| shift.moved(piece.state.position().offset);
+            while next < old.len() && moved(&old[next]).is_none_or(|offset| offset < at) {
+                next += 1;
+            }
+
+            /* several pieces can start at one offset, as when closing delimiters at the end.
+               Those at the end of the text were lexed knowing it ended there, so aren't met */
+            let rejoin = old[next..].iter()
+                .take_while(|piece| at < self.text.len() && moved(piece) == Some(at))
+                .position(|piece| {
+                    let mut moved = piece.state.clone();
+                    moved.shift(shift);
+                    moved == state
+                });
+            if let Some(rejoin) = rejoin {
+                let mut rest = old.split_off(next + rejoin);
+                rest.iter_mut().for_each(|piece| shift.piece(piece));
+                self.pieces.extend(rest);
+                return lexed;
+            }
+
+            let mut tokens = Vec::new();
+            let end = loop {
+                let token = lexer.next_token();
+                let end = matches!(token, Ok(Token { kind: TokenType::EOF, .. }));
+                tokens.push(token.map(Token::into_owned));
+                if end || lexer.at_boundary() {
+                    break end;
+                }
+            };
+
+            lexed += tokens.len();
+            self.pieces.push(Piece { state, tokens, diagnostics: lexer.take_diagnostics() });
+            if end {
+                return lexed;
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// Reserved words shared by the lexer and any tooling built on idiom_core
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeywordsKind {
+    /* Declarations and Control Flow */
+    Go,
+    Do,
+    When,
+    Or,
+    Else,
+    Dec,
+    Entry,
+    If,
+    While,
+    Return,
+
+    /* Types */
+    Void,
+    Int,
+    Bool,
+    Char,
+
+    /* Literal Values */
+    True,
+    False,
+    Null,
+
+    /* Builtins */
+    Hd,
+    Tl,
+    Fst,
+    Snd,
+    Put,
+    Get,
+}
+
+pub const KEYWORDS: &[(&str, KeywordsKind)] = &[
+    ("go", KeywordsKind::Go),
+    ("do", KeywordsKind::Do),
+    ("when", KeywordsKind::When),
+    ("or", KeywordsKind::Or),
+    ("else", KeywordsKind::Else),
+    ("dec", KeywordsKind::Dec),
+    ("entry", KeywordsKind::Entry),
+    ("if", KeywordsKind::If),
+    ("while", KeywordsKind::While),
+    ("return", KeywordsKind::Return),
+    ("void", KeywordsKind::Void),
+    ("int", KeywordsKind::Int),
+    ("bool", KeywordsKind::Bool),
+    ("char", KeywordsKind::Char),
+    ("true", KeywordsKind::True),
+    ("false", KeywordsKind::False),
+    ("null", KeywordsKind::Null),
+    ("hd", KeywordsKind::Hd),
+    ("tl", KeywordsKind::Tl),
+    ("fst", KeywordsKind::Fst),
+    ("snd", KeywordsKind::Snd),
+    ("put", KeywordsKind::Put),
+    ("get", KeywordsKind::Get),
+];
+
+impl KeywordsKind {
+    pub fn lookup(word: &str) -> Option<KeywordsKind> {
+        KEYWORDS.iter().find(|(w, _)| *w == word).map(|(_, kind)| *kind)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        KEYWORDS.iter().find(|(_, kind)| kind == self).map(|(w, _)| *w).unwrap_or_default()
+    }
+}
+
+pub fn is_keyword(word: &str) -> bool {
+    KeywordsKind::lookup(word).is_some()
+}
@@ -1,4 +1,6 @@
 
+use std::borrow::Cow;
+
 use crate::lexer::*;
 
 #[derive(Debug, Clone)]
@@ -9,6 +11,10 @@ pub struct Lexer<'a> {
 
     // Raw Index position
     pub codepoint_offset: usize,
+    pub byte_offset: usize,
+
+    // Where the current line starts in the document, which a resumed source may not reach back to
+    line_begin: usize,
 
     // Numeric Values
     pub b64: bool,
@@ -16,17 +22,109 @@ pub struct Lexer<'a> {
     pub seen_exp: bool,
     pub radix: u32,
 
-    chars: std::iter::Peekable<std::str::Chars<'a>>,
-    parameter_state: std::collections::HashMap<char, ParameterDepthType>,
+    // Scanned by byte offset, tokens borrow their text from it. A resumed lexer's source
+    // may start partway into the document, `origin` bytes in
+    source: &'a str,
+    origin: usize,
+    config: LexerConfig,
+
+    // ASCII characters the config gives a meaning to, one bit each, so words can skip the lookups
+    claimed: u128,
+
+    delimiters: Vec<OpenDelimiter>,
+    dash_mode: DashMode,
+
+    // Error recovery, problems are collected instead of returned
+    recovering: bool,
+    diagnostics: Vec<LexerError>,
+
+    // Keep whitespace as trivia on the tokens instead of skipping it
+    lossless: bool,
+
+    // Where the token currently being lexed began
+    token_start: Position,
+
+    // Whether the last token produced could end an expression
+    prev_operand: bool,
+
+    // Set once iteration has handed out EOF
+    finished: bool,
+
+    // Indentation mode, the open indentation levels and tokens owed before the next real one
+    indents: Vec<String>,
+    indent_char: Option<char>,
+    layout: std::collections::VecDeque<TokenType<'a>>,
+    held: Option<Vec<Trivia<'a>>>,
+    last_line: usize,
+    terminated: bool,
+}
+
+/* What the lexer carries from one token to the next: open delimiters, indentation levels
+   and the context settling the next '-'. Strings and comments are always lexed whole, so
+   between tokens there is no half finished literal or comment to remember */
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexerState {
+    position: Position,
+    codepoint_offset: usize,
+    line_begin: usize,
+    delimiters: Vec<OpenDelimiter>,
+    dash_mode: DashMode,
+    recovering: bool,
+    lossless: bool,
+    prev_operand: bool,
+    indents: Vec<String>,
+    indent_char: Option<char>,
+    last_line: usize,
+    terminated: bool,
+}
+
+impl LexerState {
+    // Where the next token's leading trivia starts
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    // Where the line `position` is on starts
+    pub fn line_begin(&self) -> usize {
+        self.line_begin
+    }
+
+    /* The earliest text a lexer resumed from here can look back at: the start of the line,
+       while its indentation is still to be read, otherwise nothing before `position` */
+    pub(crate) fn lookback(&self) -> usize {
+        match self.last_line < self.position.line {
+            true => self.line_begin,
+            false => self.position.offset,
+        }
+    }
+
+    pub fn delimiters(&self) -> &[OpenDelimiter] {
+        &self.delimiters
+    }
+
+    // Indentation of each open block, outermost first
+    pub fn indents(&self) -> &[String] {
+        &self.indents
+    }
+
+    // Moves the state along with text an edit has shifted
+    pub(crate) fn shift(&mut self, shift: &Shift) {
+        shift.position(&mut self.position);
+        self.codepoint_offset = self.codepoint_offset.saturating_add_signed(shift.chars);
+        self.line_begin = shift.moved(self.line_begin).unwrap_or(self.line_begin);
+        self.delimiters.iter_mut().for_each(|open| shift.span(&mut open.span));
+        if self.last_line != 0 {
+            self.last_line = self.last_line.saturating_add_signed(shift.lines);
+        }
+    }
 }
 
 macro_rules! ingest {
     ($self:ident, $($inner:tt),*) => {
-        if let Some(c) = $self.chars.peek() {
+        if let Some(c) = $self.peek() {
             if ingest!(impl c, $($inner),*) {
-                let temp = *c;
                 $self.consume_space();
-                Some(temp)
+                Some(c)
             } else {
                 None
             }
@@ -36,293 +134,1027 @@ macro_rules! ingest {
     };
 
     (impl , ) => (false);
-    (impl $c:ident, $item:tt) => (*$c == $item);
+    (impl $c:ident, $item:tt) => ($c == $item);
     (impl $c:ident, $item:tt, $($rest:tt), +) => (ingest!(impl $c, $item) || ingest!(impl $c, $($rest),+));
 }
 
 
 impl<'a> Lexer<'a> {
     pub fn new(chars: &'a str) -> Lexer<'a> {
+        Lexer::with_config(chars, LexerConfig::default())
+    }
+
+    pub fn with_config(chars: &'a str, config: LexerConfig) -> Lexer<'a> {
         Lexer {
             cur_col: 1,
             cur_line: 1,
             codepoint_offset: 0,
+            byte_offset: 0,
+            line_begin: 0,
             radix: 10,
             b64: false,
             seen_dot: false,
             seen_exp: false,
 
-            chars: chars.chars().peekable(),
-            parameter_state: std::collections::HashMap::new()
+            source: chars,
+            origin: 0,
+            claimed: config.delimiters.iter().flat_map(|pair| [pair.open, pair.close]).chain(config.separators.iter().copied())
+                .filter(char::is_ascii)
+                .fold(0, |claimed, c| claimed | 1 << c as u32),
+            config,
+            delimiters: Vec::new(),
+            dash_mode: DashMode::Auto,
+            recovering: false,
+            diagnostics: Vec::new(),
+            lossless: false,
+            token_start: Position { offset: 0, line: 1, col: 1 },
+            prev_operand: false,
+            finished: false,
+            indents: Vec::new(),
+            indent_char: None,
+            layout: std::collections::VecDeque::new(),
+            held: None,
+            last_line: 0,
+            terminated: true,
         }
     }
 
-    fn parse_string(&mut self) -> Result<TokenType, LexerError> {
-        let mut buf = String::new();
+    /* Picks up from `state`, with `source` holding the document from byte `origin` on.
+       The state's position must not come before `origin` */
+    pub fn resume(source: &'a str, origin: usize, config: LexerConfig, state: LexerState) -> Lexer<'a> {
+        let mut lexer = Lexer::with_config(source, config);
+        lexer.origin = origin;
+        lexer.byte_offset = state.position.offset.saturating_sub(origin);
+        lexer.cur_line = state.position.line;
+        lexer.cur_col = state.position.col;
+        lexer.codepoint_offset = state.codepoint_offset;
+        lexer.line_begin = state.line_begin;
+        lexer.token_start = state.position;
+        lexer.delimiters = state.delimiters;
+        lexer.dash_mode = state.dash_mode;
+        lexer.recovering = state.recovering;
+        lexer.lossless = state.lossless;
+        lexer.prev_operand = state.prev_operand;
+        lexer.indents = state.indents;
+        lexer.indent_char = state.indent_char;
+        lexer.last_line = state.last_line;
+        lexer.terminated = state.terminated;
+        lexer
+    }
+
+    // Only meaningful at a boundary, see at_boundary
+    pub fn snapshot(&self) -> LexerState {
+        LexerState {
+            position: self.location(),
+            codepoint_offset: self.codepoint_offset,
+            line_begin: self.line_begin,
+            delimiters: self.delimiters.clone(),
+            dash_mode: self.dash_mode,
+            recovering: self.recovering,
+            lossless: self.lossless,
+            prev_operand: self.prev_operand,
+            indents: self.indents.clone(),
+            indent_char: self.indent_char,
+            last_line: self.last_line,
+            terminated: self.terminated,
+        }
+    }
+
+    // Nothing is held back for the token to come, so lexing may stop here and resume later
+    pub fn at_boundary(&self) -> bool {
+        self.held.is_none() && self.layout.is_empty()
+    }
+
+    pub fn config(&self) -> &LexerConfig {
+        &self.config
+    }
+
+    // Lets a parser settle what the next '-' means when it knows better than the lexer
+    pub fn set_dash_mode(&mut self, mode: DashMode) {
+        self.dash_mode = mode;
+    }
+
+    pub fn dash_mode(&self) -> DashMode {
+        self.dash_mode
+    }
+
+    // With recovery on, errors become Error tokens and lexing carries on past them
+    pub fn set_recovery(&mut self, recovering: bool) {
+        self.recovering = recovering;
+    }
+
+    /* Lossless lexing attaches trivia to every token, so the source can be rebuilt from them.
+       Every character has to land in some token for that, so it recovers from errors whatever
+       set_recovery says. The recovery setting itself is left alone and applies again once
+       lossless lexing is turned off */
+    pub fn set_lossless(&mut self, lossless: bool) {
+        self.lossless = lossless;
+    }
+
+    fn recovering(&self) -> bool {
+        self.recovering || self.lossless
+    }
+
+    // A token exactly as written, trivia included
+    pub fn lossless_text(&self, token: &Token<'_>) -> String {
+        let mut text = String::new();
+        token.leading.iter().for_each(|trivia| text += &trivia.text);
+        text += self.slice(token.span);
+        token.trailing.iter().for_each(|trivia| text += &trivia.text);
+        text
+    }
+
+    pub fn diagnostics(&self) -> &[LexerError] {
+        &self.diagnostics
+    }
+
+    pub fn take_diagnostics(&mut self) -> Vec<LexerError> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    // Not `position`, which Iterator already claims
+    pub fn location(&self) -> Position {
+        Position { offset: self.origin + self.byte_offset, line: self.cur_line, col: self.cur_col }
+    }
+
+    // Span from the start of the current token up to the cursor
+    fn span(&self) -> Span {
+        Span { start: self.token_start, end: self.location() }
+    }
+
+    // Source text exactly as written, for tools that re-emit it
+    pub fn slice(&self, span: Span) -> &'a str {
+        let start = span.start.offset.saturating_sub(self.origin);
+        let end = span.end.offset.saturating_sub(self.origin);
+        self.source.get(start..end).unwrap_or_default()
+    }
+
+    // Decodes the escape after a '\\', `start` being where the backslash sits
+    fn parse_escape(&mut self, start: Position) -> Result<char, LexerError> {
+        let mut sequence = "\\".to_string();
+        let escaped = match self.consume_space() {
+            Some(c) => { sequence.push(c); c },
+            None => '\0',
+        };
+
+        let decoded = match escaped {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            '0' => Some('\0'),
+            '\\' | '"' | '\'' => Some(escaped),
+            'u' if self.peek() == Some('{') => {
+                while let Some(c) = self.peek() {
+                    if !(c.is_ascii_hexdigit() || c == '{' || c == '}') || sequence.ends_with('}') {
+                        break;
+                    }
+                    sequence.push(c);
+                    self.consume_space();
+                }
+
+                sequence.strip_prefix("\\u{").and_then(|s| s.strip_suffix('}'))
+                    .filter(|digits| (1..=6).contains(&digits.len()))
+                    .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                    .and_then(char::from_u32)
+            },
+            _ => None,
+        };
+
+        decoded.ok_or(LexerError::InvalidEscapeSequence { sequence, span: Span { start, end: self.location() } })
+    }
+
+    // Borrows the contents unless an escape has to be decoded, only then is a copy made
+    fn parse_string(&mut self) -> Result<TokenType<'a>, LexerError> {
+        let start = self.byte_offset;
+        let mut decoded: Option<String> = None;
+        let mut invalid = None;
+
+        loop {
+            let plain = self.byte_offset;
+            self.skip_ascii(|b| b != b'"' && b != b'\\');
+            if let Some(buf) = &mut decoded {
+                buf.push_str(&self.source[plain..self.byte_offset]);
+            }
+
+            let at = self.location();
+            let before = self.byte_offset;
+            match self.consume_space() {
+                // a bad escape is reported once the literal is closed, so lexing resumes after it
+                Some('"') => break match (invalid, decoded) {
+                    (Some(err), _) => Err(err),
+                    (None, Some(buf)) => Ok(TokenType::String(Cow::Owned(buf))),
+                    (None, None) => Ok(TokenType::String(Cow::Borrowed(&self.source[start..before]))),
+                },
+                Some('\\') => {
+                    let buf = decoded.get_or_insert_with(|| self.source[start..before].to_string());
+                    match self.parse_escape(at) {
+                        Ok(c) => buf.push(c),
+                        Err(err) => { invalid.get_or_insert(err); },
+                    }
+                },
+                Some(c) => if let Some(buf) = &mut decoded {
+                    buf.push(c);
+                },
+                None => break Err(LexerError::StringLiteralCollapse{expected: "\"".to_string(), received: TokenType::EOF, span: self.span()})
+            }
+        }
+    }
 
+    fn parse_identifier(&mut self) -> TokenType<'a> {
         loop {
-            match self.chars.next() {
-                Some('"') => break Ok(TokenType::String(buf)),
-                Some(c) => buf.push(c),
-                None => break Err(LexerError::StringLiteralCollapse{expected: "\"".to_string(), received: TokenType::EOF})
+            self.skip_ascii(|b| b.is_ascii_alphanumeric() || b == b'_');
+            match self.peek() {
+                Some(c) if !c.is_ascii() && c.is_alphanumeric() => { self.consume_space(); },
+                _ => break,
             }
         }
+
+        let raw = self.slice(self.span());
+        match KeywordsKind::lookup(raw) {
+            // booleans are one bit binary numerics, the source spelling stays available through slice()
+            Some(KeywordsKind::True) => TokenType::Numeric{ raw: Cow::Borrowed("1"), base: NumericBase::Binary, kind: NumericKind::Bool, suffix: None },
+            Some(KeywordsKind::False) => TokenType::Numeric{ raw: Cow::Borrowed("0"), base: NumericBase::Binary, kind: NumericKind::Bool, suffix: None },
+            Some(keyword) => TokenType::Keywords(keyword),
+            None => TokenType::Identifiers(Cow::Borrowed(raw)),
+        }
+    }
+
+    // Input after the cursor
+    fn rest(&self) -> &'a str {
+        self.source.get(self.byte_offset..).unwrap_or_default()
     }
 
-    fn map_base_to_num(n: &NumericBase) -> u32 {
-        match n {
-            NumericBase::Binary => 2,
-            NumericBase::Octal => 8,
-            NumericBase::Decimal => 10,
-            NumericBase::Hexadecimal => 16,
-            NumericBase::Base64 => 64,
-            _ => panic!("Invalid Base To Number Mapping")
+    // Most source is ASCII, which is read straight off the byte without decoding
+    fn peek(&self) -> Option<char> {
+        match self.source.as_bytes().get(self.byte_offset) {
+            Some(b) if b.is_ascii() => Some(*b as char),
+            Some(_) => self.rest().chars().next(),
+            None => None,
         }
     }
 
-    fn map_num2base(n: &u32) -> NumericBase {
-         match n {
-            2 => NumericBase::Binary,
-            8 => NumericBase::Octal,
-            10 => NumericBase::Decimal,
-            16 => NumericBase::Hexadecimal,
-            64 => NumericBase::Base64,
-            _ => panic!("Invalid Number to Base Mapping")
-        }   
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
     }
 
-    fn map_num_to_base(n: &str) -> Result<NumericBase, LexerError> {
-        match n {
-            "2" => Ok(NumericBase::Binary),
-            "8" => Ok(NumericBase::Octal),
-            "10" => Ok(NumericBase::Decimal),
-            "16" => Ok(NumericBase::Hexadecimal),
-            "64" => Ok(NumericBase::Base64),
-            _ => Err(LexerError::InvalidNumericBase { base: n.to_string() })
+    fn parse_character(&mut self) -> Result<TokenType<'a>, LexerError> {
+        let mut chars = Vec::new();
+        let mut invalid = None;
+
+        loop {
+            let at = self.location();
+            match self.peek() {
+                // character literals never span lines
+                None | Some('\n') => return Err(LexerError::UnterminatedCharacterLiteral{ raw: self.slice(self.span()).to_string(), span: self.span() }),
+                Some('\'') => { self.consume_space(); break; },
+                Some('\\') => {
+                    self.consume_space();
+                    match self.parse_escape(at) {
+                        Ok(c) => chars.push(c),
+                        Err(err) => { invalid.get_or_insert(err); },
+                    }
+                },
+                Some(c) => { chars.push(c); self.consume_space(); },
+            }
+        }
+
+        if let Some(err) = invalid {
+            return Err(err);
+        }
+
+        match chars.as_slice() {
+            [c] => Ok(TokenType::Character(*c)),
+            [] => Err(LexerError::EmptyCharacterLiteral{ span: self.span() }),
+            _ => Err(LexerError::MultiCharacterLiteral{ raw: self.slice(self.span()).to_string(), span: self.span() }),
         }
     }
 
-    fn digest_digit(&mut self, empty: bool) -> Result<String, LexerError> {
-        let mut raw = String::new();
+    fn map_num_to_base(n: &str) -> Option<NumericBase> {
+        if !n.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        n.parse::<u32>().ok().and_then(NumericBase::from_radix)
+    }
+
+    // Steps over the digits of a literal, they are read back out of the source afterwards
+    fn digest_digit(&mut self, empty: bool) -> Result<(), LexerError> {
+        let start = self.byte_offset;
         loop{
-            match self.chars.peek(){
-                None => {
-                    break if empty || raw.len() > 0 {
-                        Ok(raw)
-                    } else {
-                        Err(LexerError::NumericLiteralCollapse{ 
-                            received: TokenType::EOF,
-                            expected: Numeric {
-                                raw: "<int>".to_string(),
-                                base: NumericBase::Any,
-                                kind: NumericKind::Any,
-                            }
-                        })
-                    }
+            if !self.b64 {
+                self.skip_ascii(|b| b.is_ascii_digit() || b == b'_');
+            }
+
+            match self.peek(){
+                // '.' is handled by parse_numbers, 'b' and 'e' introduce bases and exponents of decimals
+                // Base64 runs to a boundary so stray characters are reported rather than lexed apart
+                Some(c) if self.b64 && ends_base64(c) => {},
+                Some(_) if self.b64 => {
+                    self.consume_space();
+                    continue;
                 },
-                Some(c) if c.is_whitespace() || (*c == 'b' || *c == 'B' || *c == 'e' || *c == 'E' || *c == '.') && !self.seen_dot => break Ok(raw),
-                Some(c) if (c.is_digit(self.radix) || self.b64 ) || !c.is_whitespace() => { 
-                    raw.push(*c); 
+                Some(c) if c.is_whitespace() || c == '.' => {},
+                Some(c) if (c == 'b' || c == 'B' || c == 'e' || c == 'E') && self.radix == 10 => {},
+                Some(c) if c.is_alphanumeric() || c == '_' => {
                     self.consume_space(); 
+                    continue;
                 },
-                Some(c) => {
-                    break Err(LexerError::UnknownNumericLiteral{ raw, received: *c })
-                },
+                // anything else (operators, delimiters) ends the literal
+                _ => {},
+            }
+
+            break match self.peek() {
+                _ if empty || self.byte_offset > start => Ok(()),
+                None => Err(LexerError::NumericLiteralCollapse{ 
+                    received: TokenType::EOF,
+                    expected: Box::new(Numeric {
+                        raw: "<int>".to_string(),
+                        base: NumericBase::Any,
+                        kind: NumericKind::Any,
+                        suffix: None,
+                    }),
+                    span: self.span(),
+                }),
+                Some(c) => Err(LexerError::UnknownNumericLiteral{ raw: self.slice(self.span()).to_string(), received: c, span: self.span() }),
             }
         }
     }
 
-    fn digit_digest(&mut self, raw: &String) -> Result<(), LexerError> {
+    fn digit_digest(&mut self, raw: &str) -> Result<(), LexerError> {
         let mut local_dot = false;
+        let span = self.span();
 
-        for c in raw.chars() {
-            if self.b64 { break; }
+        let mut digits = raw.chars().peekable();
+        let mut previous = None;
+
+        while let Some(c) = digits.next() {
+            let before = previous.replace(c);
+            if self.b64 {
+                if base64_digit(c).is_none() {
+                    return Err(LexerError::InvalidBase64Digit{ raw: raw.to_string(), invalid: c.to_string(), span });
+                }
+                continue;
+            }
+
+            // separators only ever sit between two digits, '1_000', '2b1010_1100'
+            if c == '_' {
+                let between_digits = before.is_some_and(|b: char| b.is_digit(self.radix)) && digits.peek().is_some_and(|n| n.is_digit(self.radix));
+                if !between_digits {
+                    return Err(LexerError::MisplacedDigitSeparator{ raw: raw.to_string(), span });
+                }
+                continue;
+            }
 
             if (c == 'e' || c == 'E' || c == '+' || c == '-') && self.seen_exp { } 
             else if !c.is_digit(self.radix) && c != '.' {
                 match self.radix {
-                    2 => return Err(LexerError::InvalidBinaryValue{ raw: raw.to_string(), invalid: c.to_string() }),
-                    8 => return Err(LexerError::InvalidOctalValue{ raw: raw.to_string(), invalid: c.to_string() }),
-                    10 => return Err(LexerError::InvalidDecimalValue{ raw: raw.to_string(), invalid: c.to_string() }),
-                    16 =>  return Err(LexerError::InvalidHexadecimalValue{ raw: raw.to_string(), invalid: c.to_string() }),
-                    _ => return Err(LexerError::InvalidNumericLiteral{ base: Lexer::<'a>::map_num2base(&self.radix), raw: raw.to_string(), received: c.to_string() })
+                    2 => return Err(LexerError::InvalidBinaryValue{ raw: raw.to_string(), invalid: c.to_string(), span }),
+                    8 => return Err(LexerError::InvalidOctalValue{ raw: raw.to_string(), invalid: c.to_string(), span }),
+                    10 => return Err(LexerError::InvalidDecimalValue{ raw: raw.to_string(), invalid: c.to_string(), span }),
+                    16 =>  return Err(LexerError::InvalidHexadecimalValue{ raw: raw.to_string(), invalid: c.to_string(), span }),
+                    _ => return Err(LexerError::InvalidNumericLiteral{ base: NumericBase::from_radix(self.radix).unwrap_or(NumericBase::Any), raw: raw.to_string(), received: c.to_string(), span })
                 }
             }
 
             if c == '.' && !local_dot { local_dot = true; } 
-            else if c == '.' && local_dot { return Err(LexerError::InvalidFractionalValue{ raw: raw.to_string(), received: c.to_string() }) }
+            else if c == '.' && local_dot { return Err(LexerError::InvalidFractionalValue{ raw: raw.to_string(), received: c.to_string(), span }) }
         }
         Ok(())
     }
 
-    fn parse_numbers(&mut self, start: char) -> Result<TokenType, LexerError> {
+    // A dot and a digit after a literal that can't take them, the whole run is reported
+    fn stray_fraction(&mut self) -> Result<(), LexerError> {
+        if self.peek() != Some('.') || !self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) {
+            return Ok(());
+        }
+
+        while let Some(c) = self.peek() {
+            if !(c.is_alphanumeric() || c == '.') {
+                break;
+            }
+            self.consume_space();
+        }
+        Err(LexerError::InvalidFractionalValue{ raw: self.slice(self.span()).to_string(), received: ".".to_string(), span: self.span() })
+    }
+
+    // The digits are borrowed from the source, past any base prefix and before any suffix
+    fn parse_numbers(&mut self, start: char) -> Result<TokenType<'a>, LexerError> {
         self.radix = 10;
         self.b64 = false;
         self.seen_dot = false;
         self.seen_exp = false;
-        let mut raw = start.to_string();
+        let mut digits = self.token_start.offset - self.origin;
         let mut kind = NumericKind::Whole;
         let mut base = NumericBase::Decimal;
 
+        // parse fractions written without a leading zero, '.798'
+        if start == '.' {
+            self.seen_dot = true;
+            self.digest_digit(false)?;
+            kind = NumericKind::Fractional;
+        }
+
         // parse leading numerical values
-        if start.is_digit(self.radix){
-            raw += &self.digest_digit(true)?;
-            
-            // parse decimal values
-            if let Some(c) = ingest!(self, '.') {
-                raw.push(c);
+        if start.is_digit(self.radix) || start == '.' {
+            if start != '.' {
+                self.digest_digit(true)?;
+            }
+
+            // parse decimal values, a dot without digits behind it terminates the statement instead
+            if !self.seen_dot && self.peek() == Some('.') && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) {
+                self.consume_space();
                 self.seen_dot = true;
-                raw += &self.digest_digit(false)?;
+                self.digest_digit(false)?;
                 kind = NumericKind::Fractional;
             }
 
+            // a fraction running into more digits is ambiguous ('1.2.3'), while a dot after it ends the statement ('0.5.')
+            if self.seen_dot {
+                self.stray_fraction()?;
+            }
+
             // parse exponential values
-            if let Some(c) = ingest!(self, 'e', 'E') {
+            if ingest!(self, 'e', 'E').is_some() {
                 kind = NumericKind::Exponential;
                 base = NumericBase::Decimal;
                 self.seen_exp = true;
-                raw.push(c);
-                
-                if let Some(c) = ingest!(self, '+', '-') {
-                    raw.push(c);
-                }
-
-                raw += &self.digest_digit(false)?;
+                ingest!(self, '+', '-');
+                self.digest_digit(false)?;
             }
             
             // parse variable base values
-            if let Some(_c) = ingest!(self, 'b', 'B') {  // explicit base declaration
-                let raw_base = raw.clone();
-                raw.clear();
+            if kind == NumericKind::Whole && ingest!(self, 'b', 'B').is_some() {  // explicit base declaration
+                let raw_base = &self.source[digits..self.byte_offset - 1];
+                digits = self.byte_offset;
 
-                base = Lexer::<'a>::map_num_to_base(&raw_base)?;
+                base = match Lexer::<'a>::map_num_to_base(raw_base) {
+                    Some(base) => base,
+                    None => return Err(LexerError::InvalidNumericBase { base: raw_base.to_string(), span: self.span() }),
+                };
 
                 if base == NumericBase::Base64 { 
                     self.b64 = true; 
                     self.radix = 10; 
                 } else {       
-                    self.radix = Lexer::<'a>::map_base_to_num(&base); 
+                    self.radix = base.radix(); 
                 }
 
-                raw += &self.digest_digit(false)?;
+                self.digest_digit(false)?;
+
+                // only decimals have fractions, '16b16.4'
+                self.stray_fraction()?;
             }
         } else {
-            println!("Compiler Bug .. Not sure how we had a number that isn't a number?!");
             return Err(LexerError::InvalidNumericLiteral {
                 base,
-                raw,
+                raw: start.to_string(),
                 received: start.to_string(),
+                span: self.span(),
             });
         }
-        let _ = &self.digit_digest(&raw)?;
-        Ok(TokenType::Numeric{ raw, base, kind })
+        let mut raw = &self.source[digits..self.byte_offset];
+        let suffix = self.split_suffix(&mut raw);
+        self.digit_digest(raw)?;
+
+        if let Some(suffix) = suffix {
+            let token = Token::new(TokenType::Numeric{ raw: Cow::Borrowed(raw), base: base.clone(), kind: kind.clone(), suffix: None }, self.span());
+            if !evaluate(&token).is_ok_and(|evaluated| suffix.can_represent(&evaluated.value)) {
+                return Err(LexerError::UnrepresentableLiteral{ raw: self.slice(self.span()).to_string(), suffix, span: self.span() });
+            }
+        }
+
+        Ok(TokenType::Numeric{ raw: Cow::Borrowed(raw), base, kind, suffix })
+    }
+
+    // Takes a trailing 'u8', 'i32', 'f64'.. off the digits. A suffix starting with a
+    // digit of the radix ('f' in hexadecimal) is read as digits instead
+    fn split_suffix(&self, raw: &mut &'a str) -> Option<NumericSuffix> {
+        if self.b64 {
+            return None;
+        }
+
+        let (text, suffix) = NUMERIC_SUFFIXES.iter().find(|(text, _)| {
+            raw.len() > text.len() && raw.ends_with(text) && !text.starts_with(|c: char| c.is_digit(self.radix))
+        })?;
+        *raw = &raw[..raw.len() - text.len()];
+        Some(*suffix)
     }
     
-    fn map_delimiters(c: &char) -> char {
-        match c {
-            '|' => '!', // Open Function
-            '~' => '|', // Close Function
-            ',' => '-', // Open Parameters
-            '-' => ',', // Close Paremeters
-            '{' => '}', // Open Object
-            '}' => '{', // Close Object
-            '<' => '>', // Open Vector
-            '>' => '<', // Close Vector
-            '[' => ']', // Open Array
-            ']' => '[', // Close Array
-            '(' => ')', // Open Join
-            ')' => '(', // Close Join
+    fn open_delimiters(&mut self, c: &char) -> ParameterDepthType {
+        let depth = self.depth(*c);
+        self.delimiters.push(OpenDelimiter { raw: *c, span: self.span() });
+        depth
+    }
+
+    // Only the innermost delimiter may close, '( [ ) ]' leaves the stack as it was
+    fn close_delimiters(&mut self, c: &char) -> Result<ParameterDepthType, LexerError> {
+        let opener = match self.config.closed_by(*c) {
+            Some(pair) => pair.open,
+            None => return Err(LexerError::UnknownDelimiter{ symbol: *c, span: self.span() }),
+        };
+
+        let beneath = self.delimiters.len().checked_sub(2).map(|i| self.delimiters[i].raw);
+        if self.config.implicit_parameter_close && self.innermost_role() == Some(DelimiterRole::Parameters)
+            && self.innermost() != Some(opener) && beneath == Some(opener) {
+            self.delimiters.pop();
+        }
 
-            _ => panic!("How are you going to use Unmapped Delimiters that don't doesn't Map existing?")
+        match self.delimiters.last() {
+            Some(open) if open.raw == opener => {
+                self.delimiters.pop();
+                Ok(self.depth(opener))
+            },
+            Some(open) if self.depth(opener) > 0 => Err(LexerError::CrossedDelimiters{
+                symbol: *c,
+                expected: self.closer_of(open.raw),
+                open: open.span,
+                span: self.span(),
+            }),
+            _ => Err(LexerError::MisMatchedDelimiters{symbol: *c, requires: opener, span: self.span()}),
         }
     }
 
-    fn open_delimiters(&mut self, c: &char) -> ParameterDepthType {
-        if let Some(v) = self.parameter_state.get_mut(&c) {
-            *v += 1;
-            *v - 1
-        } else {
-            self.parameter_state.insert(*c, 1);
-            0
+    fn depth(&self, c: char) -> ParameterDepthType {
+        self.delimiters.iter().filter(|open| open.raw == c).count() as ParameterDepthType
+    }
+
+    fn innermost(&self) -> Option<char> {
+        self.delimiters.last().map(|open| open.raw)
+    }
+
+    fn innermost_role(&self) -> Option<DelimiterRole> {
+        self.innermost().and_then(|c| self.config.opened_by(c)).map(|pair| pair.role)
+    }
+
+    // Only configured openers are ever pushed, so the fallback is never reached
+    fn closer_of(&self, opener: char) -> char {
+        self.config.opened_by(opener).map_or(opener, |pair| pair.close)
+    }
+
+    // Delimiters still waiting to be closed, outermost first
+    pub fn unclosed(&self) -> &[OpenDelimiter] {
+        &self.delimiters
+    }
+
+    fn unclosed_error(&self, open: &OpenDelimiter) -> LexerError {
+        LexerError::UnclosedDelimiter{ symbol: open.raw, requires: self.closer_of(open.raw), span: open.span }
+    }
+
+    // Anything left open is reported once the input runs out: collected in source
+    // order when recovering, otherwise one error per call, innermost first
+    fn end_of_input(&mut self) -> Result<TokenType<'a>, LexerError> {
+        if self.recovering() {
+            for open in std::mem::take(&mut self.delimiters) {
+                let error = self.unclosed_error(&open);
+                self.diagnostics.push(error);
+            }
+        }
+
+        match self.delimiters.pop() {
+            Some(open) => Err(self.unclosed_error(&open)),
+            None => Ok(TokenType::EOF),
         }
     }
 
-    fn close_delimiters(&mut self, c: &char) -> Result<ParameterDepthType, LexerError> {
-        if let Some(v) = self.parameter_state.get_mut(&Lexer::map_delimiters(&c)) {
-            if *v >= 1 {
-                *v -= 1;
-                Ok(*v)
-            } else {
-                Err(LexerError::MisMatchedDelimiters{symbol: *c, requires: Lexer::map_delimiters(&c)})
+    // Longest operator starting with `start` that the upcoming input spells out
+    fn longest_operator(&self, start: char) -> Option<&'static str> {
+        OPERATORS.iter().copied().find(|op| op.strip_prefix(start).is_some_and(|rest| self.upcoming(rest)))
+    }
+
+    // Whether an operand starts after the cursor, on the same line
+    fn operand_follows(&self) -> bool {
+        match self.rest().chars().find(|c| *c != ' ' && *c != '\t') {
+            Some(c) => c.is_alphanumeric() || c == '_' || c == '(' || c == '"' || c == '\'',
+            None => false,
+        }
+    }
+
+    // In Auto mode a '-' closes the innermost parameter list, unless it sits
+    // between two operands and so reads as a subtraction
+    fn dash_closes_parameters(&self, pair: &DelimiterPair) -> bool {
+        match self.dash_mode {
+            DashMode::Delimiter => true,
+            DashMode::Operator => false,
+            DashMode::Auto => self.innermost() == Some(pair.open) && !(self.prev_operand && self.operand_follows()),
+        }
+    }
+
+    fn operator_closes(&self, pair: &DelimiterPair) -> bool {
+        match pair.role {
+            DelimiterRole::Parameters => self.dash_closes_parameters(pair),
+            _ => self.innermost() == Some(pair.open),
+        }
+    }
+
+    // Operators that double as delimiters ('-', '<' and '>' by default) are settled
+    // by the shape of the operator and the surrounding context
+    fn parse_operator(&mut self, start: char) -> Result<TokenType<'a>, LexerError> {
+        let op = match self.longest_operator(start) {
+            Some(op) => op,
+            None => return Err(LexerError::UnknownPokemon{ unknowns: start.to_string(), span: self.span() }),
+        };
+
+        self.skip_ascii_bytes(op.len() - start.len_utf8());
+
+        if op.len() == start.len_utf8() {
+            if let Some(pair) = self.config.closed_by(start).copied() {
+                if self.operator_closes(&pair) {
+                    return Ok(TokenType::Delimiters{ raw: start, kind: DelimitersKind::Closing(self.close_delimiters(&start)?) });
+                }
+            }
+
+            if self.config.opened_by(start).is_some() && !self.prev_operand {
+                return Ok(TokenType::Delimiters{ raw: start, kind: DelimitersKind::Opening(self.open_delimiters(&start)) });
             }
-        } else {
-            Err(LexerError::MisMatchedDelimiters{symbol: *c, requires: Lexer::map_delimiters(&c)})
-            
         }
+
+        // the leading dash of '-->' closes the open parameter list before sending the result on
+        if op == "-->" && self.innermost_role() == Some(DelimiterRole::Parameters) {
+            if let Some(pair) = self.config.role(DelimiterRole::Parameters).filter(|pair| pair.close == '-').copied() {
+                self.close_delimiters(&pair.close)?;
+            }
+        }
+
+        Ok(TokenType::Operators(Cow::Borrowed(op)))
     }
 
+    // A character may open one pair and close another, or both open and close the
+    // same pair, in which case closing the innermost delimiter wins
+    fn parse_delimiter(&mut self, c: char) -> Result<TokenType<'a>, LexerError> {
+        let closing = self.config.closed_by(c).map(|pair| pair.open);
+        let opening = self.config.opened_by(c).is_some();
 
+        if closing.is_some() && (!opening || closing == self.innermost()) {
+            Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Closing(self.close_delimiters(&c)?) })
+        } else {
+            Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Opening(self.open_delimiters(&c)) })
+        }
+    }
+
+    pub fn transform_content(&mut self, c: char) -> Result<TokenType<'a>, LexerError> {
+        let operator = || OPERATORS.iter().any(|op| op.starts_with(c));
 
-    pub fn transform_content(&mut self, c: char) -> Result<TokenType, LexerError> {
         match c {
-            // Delimiters
-            ',' => Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Opening(self.open_delimiters(&c)) }),
-            '-' => Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Closing(self.close_delimiters(&c)?) }),
-            '|' => Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Opening(self.open_delimiters(&c)) }),
-            '~' => Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Closing(self.close_delimiters(&c)?) }),
-            '{' | '[' | '<' | '(' => Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Opening(self.open_delimiters(&c)) }),
-            '}' | ']' | '>' | ')' => Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Closing(self.close_delimiters(&c)?) }),
+            // Words are by far the most common token
+            c if (c.is_ascii_alphabetic() || c == '_') && self.claimed & 1 << c as u32 == 0 => Ok(self.parse_identifier()),
+
+            // Delimiters, those that are also operators are left to parse_operator
+            c if self.config.is_separator(c) && !operator() => Ok(TokenType::Delimiters{ raw: c, kind: DelimitersKind::Seperator }),
+            c if self.config.is_delimiter(c) && !operator() => self.parse_delimiter(c),
+            c if DELIMITER_LIKE.contains(&c) => Err(LexerError::UnknownDelimiter{ symbol: c, span: self.span() }),
 
             // Numbers
             '0' ..= '9' => self.parse_numbers(c),
+            '.' if self.peek().is_some_and(|c| c.is_ascii_digit()) => self.parse_numbers(c),
+
+            // Statement Terminator
+            '.' => Ok(TokenType::Terminator),
             
             // Strings
             '"' => self.parse_string(),
+            '\'' => self.parse_character(),
+
+            // Doc comments, plain ones are trivia and never get this far
+            '/' if self.upcoming("//") && self.peek_nth(2) != Some('/') => Ok(self.parse_doc_comment()),
 
             // Operators
-            
+            _ if operator() => self.parse_operator(c),
 
             // Indentifiers
-            
+            c if c.is_alphabetic() || c == '_' => Ok(self.parse_identifier()),
 
-            _ => Err(LexerError::UnknownPokemon{ unknowns: c.to_string() })
+            _ => Err(LexerError::UnknownPokemon{ unknowns: c.to_string(), span: self.span() })
         }
     }
     
     fn consume_space(&mut self) -> Option<char> {
-        match self.chars.next() {
-            Some(c) => {
-                self.cur_col += 1;
+        let c = self.peek()?;
+        self.cur_col += 1;
 
-                if c == '\n' {
-                    self.cur_line += 1;
-                    self.cur_col = 1;
-                }
+        if c == '\n' {
+            self.cur_line += 1;
+            self.cur_col = 1;
+        }
 
-                self.codepoint_offset += 1;
+        self.codepoint_offset += 1;
+        self.byte_offset += c.len_utf8();
+        if c == '\n' {
+            self.line_begin = self.origin + self.byte_offset;
+        }
+        Some(c)
+    }
 
-                Some(c)
-            }
-            None => None
+    // Steps over the ASCII run ahead that satisfies `keep`, stopping short of any line break
+    fn skip_ascii(&mut self, keep: impl Fn(u8) -> bool) {
+        let run = self.rest().bytes().take_while(|b| b.is_ascii() && *b != b'\n' && keep(*b)).count();
+        self.skip_ascii_bytes(run);
+    }
+
+    // Only for text already known to be ASCII without line breaks, a column per byte
+    fn skip_ascii_bytes(&mut self, count: usize) {
+        self.byte_offset += count;
+        self.codepoint_offset += count;
+        self.cur_col += count;
+    }
+
+    // An error that stopped partway through a word ('48b' of '48b1239419') skips
+    // the rest of it, so the remainder isn't lexed as tokens of its own
+    fn resync(&mut self) {
+        let in_word = |c: char| c.is_alphanumeric() || c == '_';
+
+        if !self.slice(self.span()).chars().next_back().is_some_and(in_word) {
+            return;
         }
+        while self.peek().is_some_and(in_word) {
+            self.consume_space();
+        }
+    }
+
+
+    fn at_newline(&self) -> bool {
+        let rest = self.rest().as_bytes();
+        rest.first() == Some(&b'\n') || rest.starts_with(b"\r\n")
+    }
+
+    fn upcoming(&self, text: &str) -> bool {
+        self.rest().starts_with(text)
+    }
+
+    // '///' documents, while '//' and four or more slashes are plain comments
+    fn at_doc_comment(&self) -> bool {
+        self.upcoming("///") && self.peek_nth(3) != Some('/')
     }
 
-    fn skip_spaces(&mut self) {
-        while let Some(c) = self.chars.peek() {
-            if !c.is_whitespace() {
+    fn skip_line(&mut self) {
+        loop {
+            self.skip_ascii(|b| b != b'\r');
+            if self.peek().is_none() || self.at_newline() {
                 break;
             }
             self.consume_space();
         }
     }
 
-    pub fn next_token(&mut self) -> Result<TokenType, LexerError> {
-        self.skip_spaces();
+    // Block comments nest, so '/* a /* b */ c */' is one comment
+    fn skip_block_comment(&mut self, start: Position) -> Result<(), LexerError> {
+        let mut depth = 0;
+        loop {
+            self.skip_ascii(|b| b != b'/' && b != b'*');
+            if self.upcoming("/*") {
+                depth += 1;
+            } else if self.upcoming("*/") {
+                depth -= 1;
+            } else if self.consume_space().is_some() {
+                continue;
+            } else {
+                return Err(LexerError::UnterminatedBlockComment{ span: Span { start, end: self.location() } });
+            }
 
-        if let Some(c) = self.consume_space(){
+            self.consume_space();
+            self.consume_space();
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /* Whitespace and comments ahead of a token, or after one up to the end of its line.
+       Block comments after a token are left to lead the next one */
+    fn trivia(&mut self, trailing: bool) -> Result<Vec<Trivia<'a>>, LexerError> {
+        let mut trivia = Vec::new();
+        if trailing && !self.lossless {
+            return Ok(trivia);
+        }
+
+        // most tokens run straight into the next one, or have a single space between
+        if !self.lossless {
+            self.skip_ascii(|b| b == b' ');
+        }
+        match self.source.as_bytes().get(self.byte_offset) {
+            Some(b) if b.is_ascii() && !matches!(b, b' ' | b'\t'..=b'\r' | b'/') => return Ok(trivia),
+            _ => {},
+        }
+
+        loop {
+            let start = self.location();
+            let kind = match self.peek() {
+                _ if self.at_newline() => {
+                    if self.consume_space() == Some('\r') {
+                        self.consume_space();
+                    }
+                    TriviaKind::Newline
+                },
+                Some(c) if c.is_whitespace() => {
+                    loop {
+                        self.skip_ascii(|b| b == b' ' || b == b'\t');
+                        if !self.peek().is_some_and(char::is_whitespace) || self.at_newline() {
+                            break;
+                        }
+                        self.consume_space();
+                    }
+                    TriviaKind::Whitespace
+                },
+                Some('/') if self.upcoming("//") && !self.at_doc_comment() => {
+                    self.skip_line();
+                    TriviaKind::LineComment
+                },
+                Some('/') if self.upcoming("/*") && !trailing => {
+                    if let Err(err) = self.skip_block_comment(start) {
+                        if !self.recovering() {
+                            return Err(err);
+                        }
+                        self.diagnostics.push(err);
+                    }
+                    TriviaKind::BlockComment
+                },
+                _ => break,
+            };
+
+            if self.lossless {
+                let span = Span { start, end: self.location() };
+                trivia.push(Trivia { kind, text: Cow::Borrowed(self.slice(span)), span });
+            }
+
+            if trailing && kind == TriviaKind::Newline {
+                break;
+            }
+        }
+        Ok(trivia)
+    }
+
+    fn parse_doc_comment(&mut self) -> TokenType<'a> {
+        self.consume_space();
+        self.consume_space();
+
+        let start = self.location();
+        self.skip_line();
+        let text = self.slice(Span { start, end: self.location() });
+        TokenType::DocComment(Cow::Borrowed(text.strip_prefix(' ').unwrap_or(text)))
+    }
+
+    fn line_start(&self) -> usize {
+        self.line_begin.saturating_sub(self.origin)
+    }
+
+    // Indentation of the line the cursor is on
+    fn line_indent(&self) -> String {
+        let line = self.source.get(self.line_start()..).unwrap_or_default();
+        line.chars().take_while(|c| *c == ' ' || *c == '\t').collect()
+    }
+
+    fn inconsistent(&self, found: &str) -> LexerError {
+        let start = Position { offset: self.line_begin, line: self.cur_line, col: 1 };
+        let end = Position { offset: start.offset + found.len(), line: self.cur_line, col: 1 + found.chars().count() };
+
+        LexerError::InconsistentIndentation {
+            expected: self.indents.last().cloned().unwrap_or_default(),
+            found: found.to_string(),
+            span: Span { start, end },
+        }
+    }
+
+    /* Owed once the cursor has moved onto a new line: a Terminator for the statement left
+       behind, then an Indent for a deeper line or a Dedent per level given up. Lines inside
+       brackets continue the one they started on */
+    fn line_break(&mut self) -> Result<(), LexerError> {
+        let at_end = self.peek().is_none();
+        let continued = self.delimiters.last()
+            .and_then(|open| self.config.opened_by(open.raw))
+            .is_some_and(|pair| pair.role != DelimiterRole::FunctionBody);
+
+        if continued || self.last_line == 0 || (!at_end && self.cur_line == self.last_line) {
+            return Ok(());
+        }
+        self.last_line = self.cur_line;
+
+        let indent = if at_end { String::new() } else { self.line_indent() };
+        let top = self.indents.last().cloned().unwrap_or_default();
+
+        let mixed = match (self.indent_char, indent.chars().next()) {
+            (_, None) => false,
+            (Some(c), _) => indent.chars().any(|i| i != c),
+            (None, Some(first)) => indent.chars().any(|i| i != first),
+        };
+        if mixed {
+            return Err(self.inconsistent(&indent));
+        }
+        self.indent_char = self.indent_char.or(indent.chars().next());
+
+        if indent.len() > top.len() {
+            if !indent.starts_with(&top) {
+                return Err(self.inconsistent(&indent));
+            }
+            self.indents.push(indent);
+            self.layout.push_back(TokenType::Indent);
+            return Ok(());
+        }
+
+        if !self.terminated {
+            self.layout.push_back(TokenType::Terminator);
+        }
+        while self.indents.last().is_some_and(|level| level.len() > indent.len()) {
+            self.indents.pop();
+            self.layout.push_back(TokenType::Dedent);
+        }
+
+        if self.indents.last().map_or("", String::as_str) != indent {
+            let error = self.inconsistent(&indent);
+            self.indents.push(indent);
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // Synthetic tokens take no room in the source, so they sit where the next real token starts
+    fn layout_token(&mut self) -> Result<Option<Token<'a>>, LexerError> {
+        if self.held.is_none() {
+            let leading = self.trivia(false)?;
+            self.held = Some(leading);
+
+            if let Err(err) = self.line_break() {
+                if !self.recovering() {
+                    return Err(err);
+                }
+                self.diagnostics.push(err);
+            }
+        }
+
+        let kind = match self.layout.pop_front() {
+            Some(kind) => kind,
+            None => return Ok(None),
+        };
+        self.terminated = true;
+        self.prev_operand = false;
+
+        let at = self.location();
+        Ok(Some(Token::new(kind, Span { start: at, end: at })))
+    }
+
+    // Context the token just lexed leaves for the next one
+    fn settle(&mut self, kind: &TokenType) {
+        // Doc comments sit between items and leave the context as it was
+        if !matches!(kind, TokenType::DocComment(_)) {
+            self.prev_operand = matches!(kind,
+                TokenType::Identifiers(_) | TokenType::Numeric{..} | TokenType::String(_) | TokenType::Character(_) | TokenType::Error(_) |
+                TokenType::Keywords(KeywordsKind::Null) |
+                TokenType::Delimiters{ kind: DelimitersKind::Closing(_), .. });
+            self.terminated = matches!(kind, TokenType::Terminator);
+        }
+        self.last_line = self.cur_line;
+    }
+
+    pub fn next_token(&mut self) -> Result<Token<'a>, LexerError> {
+        if self.config.indentation {
+            if let Some(token) = self.layout_token()? {
+                return Ok(token);
+            }
+        }
+
+        let leading = match self.held.take() {
+            Some(leading) => leading,
+            None => self.trivia(false)?,
+        };
+        self.token_start = self.location();
+
+        let kind = if let Some(c) = self.consume_space(){
             self.transform_content(c)
         } else {
-            Ok(TokenType::EOF)
+            self.end_of_input()
+        };
+
+        let kind = match kind {
+            Ok(kind) => kind,
+            Err(err) if self.recovering() => {
+                self.resync();
+                self.diagnostics.push(err);
+                TokenType::Error(Cow::Borrowed(self.slice(self.span())))
+            },
+            // the context moves on as it would past the Error token recovery leaves in its place
+            Err(err) => {
+                self.settle(&TokenType::Error(Cow::Borrowed("")));
+                return Err(err);
+            },
+        };
+
+        self.settle(&kind);
+        let span = self.span();
+        let trailing = if matches!(kind, TokenType::EOF) { Vec::new() } else { self.trivia(true)? };
+
+        Ok(Token { kind, span, leading, trailing })
+    }
+}
+
+// Yields every token up to and including EOF, errors along the way included
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, LexerError>;
+
+    // Inlined so a consuming loop in another crate doesn't copy every token an extra time
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
         }
+
+        let token = self.next_token();
+        self.finished = matches!(token, Ok(Token { kind: TokenType::EOF, .. }));
+        Some(token)
     }
 }
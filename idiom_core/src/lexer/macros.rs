@@ -47,21 +47,56 @@ macro_rules! call_numeric_base {
     (B64) => {
         idiom_core::NumericBase::Base64
     };
+    ($radix:literal) => {
+        idiom_core::NumericBase::Radix($radix)
+    };
 }
 
 
+#[macro_export]
+macro_rules! call_numeric_suffix {
+    (u8) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Unsigned, width: 8 } };
+    (u16) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Unsigned, width: 16 } };
+    (u32) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Unsigned, width: 32 } };
+    (u64) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Unsigned, width: 64 } };
+    (u128) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Unsigned, width: 128 } };
+    (i8) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Signed, width: 8 } };
+    (i16) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Signed, width: 16 } };
+    (i32) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Signed, width: 32 } };
+    (i64) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Signed, width: 64 } };
+    (i128) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Signed, width: 128 } };
+    (f32) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Float, width: 32 } };
+    (f64) => { idiom_core::NumericSuffix{ signedness: idiom_core::Signedness::Float, width: 64 } };
+}
+
 #[macro_export]
 macro_rules! tokenize {
     (EOF) => {
         idiom_core::TokenType::EOF
     };
 
+    (Terminator) => {
+        idiom_core::TokenType::Terminator
+    };
+
     (Char $raw:tt) => {
         idiom_core::TokenType::Character($raw)
     };
 
-    (Num $raw:tt $base:ident $kind:ident) => {
-        idiom_core::TokenType::Numeric{ raw: $raw, base: call_numeric_base!($base), kind: call_numeric_kind!($kind)}
+    (Ident $raw:tt) => {
+        idiom_core::TokenType::Identifiers($raw.into())
+    };
+
+    (Keyword $kind:ident) => {
+        idiom_core::TokenType::Keywords(idiom_core::KeywordsKind::$kind)
+    };
+
+    (Num $raw:tt $base:tt $kind:ident) => {
+        idiom_core::TokenType::Numeric{ raw: $raw.into(), base: call_numeric_base!($base), kind: call_numeric_kind!($kind), suffix: None }
+    };
+
+    (Num $raw:tt $base:tt $kind:ident $suffix:ident) => {
+        idiom_core::TokenType::Numeric{ raw: $raw.into(), base: call_numeric_base!($base), kind: call_numeric_kind!($kind), suffix: Some(call_numeric_suffix!($suffix)) }
     };
 
     (Delimit $raw:tt ($($inner:tt)+)) => {
@@ -1,76 +1,256 @@
 extern crate thiserror;
 
+#[allow(clippy::module_inception)]
 pub mod lexer;
 pub use lexer::*;
 
 pub mod macros;
-pub use macros::*;
 
+pub mod keywords;
+pub use keywords::*;
+
+pub mod numeric;
+pub use numeric::*;
+
+pub mod config;
+pub use config::*;
+
+pub mod stream;
+pub use stream::*;
+
+pub mod reader;
+pub use reader::*;
+
+pub mod document;
+pub use document::*;
+
+use std::borrow::Cow;
 use std::io;
+use std::sync::Arc;
 use core::fmt::Debug;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum LexerError {
     #[error("File IO Error")]
-    FileIO(#[from] io::Error),
+    FileIO { source: Arc<io::Error>, span: Span },
 
     #[error("Expected Symbol {expected:?} Missing!\nFound {found:?}")]
-    ImproperUsage { expected: TokenType, found: Token },
+    ImproperUsage { expected: TokenType<'static>, found: Box<Token<'static>>, span: Span },
 
     #[error("Improper Parameterization with {symbol:?}")]
-    MisMatchedDelimiters { symbol: char, requires: char },
+    MisMatchedDelimiters { symbol: char, requires: char, span: Span },
+
+    #[error("Crossed Delimiters: {symbol:?} closes across an open delimiter that requires {expected:?} first.")]
+    CrossedDelimiters { symbol: char, expected: char, open: Span, span: Span },
+
+    #[error("Unclosed Delimiter {symbol:?}, Missing {requires:?} before the End of Input.")]
+    UnclosedDelimiter { symbol: char, requires: char, span: Span },
+
+    #[error("Unknown Delimiter {symbol:?}, no pair in the Lexer Configuration uses it.")]
+    UnknownDelimiter { symbol: char, span: Span },
 
     #[error("Invalid Numeric Character for {base:?} Number: {raw:?} Fails. {received:?} is invalid.")]
-    InvalidNumericLiteral { base: NumericBase, raw: String, received: String }, // can we add expected behaviour?
+    InvalidNumericLiteral { base: NumericBase, raw: String, received: String, span: Span }, // can we add expected behaviour?
 
     #[error("Invalid Fraction: {received:?} in {raw:?}.")]
-    InvalidFractionalValue { raw: String, received: String },
+    InvalidFractionalValue { raw: String, received: String, span: Span },
+
+    #[error("Invalid Base Number. {base:?} is Not a Valid Base implimented in Idiom_Core, expected 2 through 36 or 64.")]
+    InvalidNumericBase { base: String, span: Span },
 
-    #[error("Invalid Base Number. {base:?} is Not a Valid Base implimented in Idiom_Core.")]
-    InvalidNumericBase { base: String },
+    #[error("Misplaced Digit Separator in {raw:?}, '_' must sit between two digits.")]
+    MisplacedDigitSeparator { raw: String, span: Span },
+
+    #[error("Numeric Literal {raw:?} cannot be represented as {suffix}.")]
+    UnrepresentableLiteral { raw: String, suffix: NumericSuffix, span: Span },
 
     #[error("Invalid Binary Value: {invalid:?} in {raw:?}")]
-    InvalidBinaryValue { raw: String, invalid: String },
+    InvalidBinaryValue { raw: String, invalid: String, span: Span },
 
     #[error("Invalid Octal Value: {invalid:?} in {raw:?}")]
-    InvalidOctalValue { raw: String, invalid: String },
+    InvalidOctalValue { raw: String, invalid: String, span: Span },
     
     #[error("Invalid Decimal Value: {invalid:?} in {raw:?}")]
-    InvalidDecimalValue { raw: String, invalid: String },
+    InvalidDecimalValue { raw: String, invalid: String, span: Span },
     
     #[error("Invalid Hexadecimal Value: {invalid:?} in {raw:?}")]
-    InvalidHexadecimalValue { raw: String, invalid: String },
+    InvalidHexadecimalValue { raw: String, invalid: String, span: Span },
+
+    #[error("Invalid Base64 Digit: {invalid:?} in {raw:?}")]
+    InvalidBase64Digit { raw: String, invalid: String, span: Span },
 
     #[error("Numerical Literal Collapsed. Found: {received:?}, Expected: {expected:?}")]
-    NumericLiteralCollapse{ received: TokenType, expected: Numeric },
+    NumericLiteralCollapse{ received: TokenType<'static>, expected: Box<Numeric>, span: Span },
 
     #[error("String Literal Collapsed. Missing Expected Symbol. Expected: {expected:?}. Found: {received:?}.")]
-    StringLiteralCollapse{ expected: String, received: TokenType },
+    StringLiteralCollapse{ expected: String, received: TokenType<'static>, span: Span },
+
+    #[error("Invalid Escape Sequence: {sequence:?}")]
+    InvalidEscapeSequence { sequence: String, span: Span },
+
+    #[error("Empty Character Literal.")]
+    EmptyCharacterLiteral { span: Span },
+
+    #[error("Character Literal {raw:?} holds more than one Character.")]
+    MultiCharacterLiteral { raw: String, span: Span },
+
+    #[error("Character Literal {raw:?} is Missing its closing '.")]
+    UnterminatedCharacterLiteral { raw: String, span: Span },
+
+    #[error("Inconsistent Indentation {found:?}, Expected {expected:?} or a deeper level using the same character.")]
+    InconsistentIndentation { expected: String, found: String, span: Span },
+
+    #[error("Unterminated Block Comment, Missing '*/' before the End of Input.")]
+    UnterminatedBlockComment { span: Span },
+
+    #[error("Nested Too Deeply, expressions and blocks may only nest {limit} levels.")]
+    NestedTooDeeply { limit: usize, span: Span },
 
     #[error("Unexpected Numeric Digest: {raw:?}, Received: {received:?}")]
-    UnknownNumericLiteral{ raw: String, received: char },
+    UnknownNumericLiteral{ raw: String, received: char, span: Span },
 
     #[error("Unidentified Token - {unknowns:?}")]
-    UnknownPokemon { unknowns: String }
+    UnknownPokemon { unknowns: String, span: Span }
+}
+
+impl LexerError {
+    /* Location of the offending source text */
+    pub fn span(&self) -> Span {
+        *self.clone().span_mut()
+    }
+
+    fn span_mut(&mut self) -> &mut Span {
+        match self {
+            LexerError::FileIO { span, .. } |
+            LexerError::ImproperUsage { span, .. } |
+            LexerError::MisMatchedDelimiters { span, .. } |
+            LexerError::CrossedDelimiters { span, .. } |
+            LexerError::UnclosedDelimiter { span, .. } |
+            LexerError::UnknownDelimiter { span, .. } |
+            LexerError::InvalidNumericLiteral { span, .. } |
+            LexerError::InvalidFractionalValue { span, .. } |
+            LexerError::InvalidNumericBase { span, .. } |
+            LexerError::MisplacedDigitSeparator { span, .. } |
+            LexerError::UnrepresentableLiteral { span, .. } |
+            LexerError::InvalidBinaryValue { span, .. } |
+            LexerError::InvalidOctalValue { span, .. } |
+            LexerError::InvalidDecimalValue { span, .. } |
+            LexerError::InvalidHexadecimalValue { span, .. } |
+            LexerError::InvalidBase64Digit { span, .. } |
+            LexerError::NumericLiteralCollapse { span, .. } |
+            LexerError::StringLiteralCollapse { span, .. } |
+            LexerError::InvalidEscapeSequence { span, .. } |
+            LexerError::EmptyCharacterLiteral { span } |
+            LexerError::MultiCharacterLiteral { span, .. } |
+            LexerError::UnterminatedCharacterLiteral { span, .. } |
+            LexerError::UnknownNumericLiteral { span, .. } |
+            LexerError::InconsistentIndentation { span, .. } |
+            LexerError::UnterminatedBlockComment { span } |
+            LexerError::NestedTooDeeply { span, .. } |
+            LexerError::UnknownPokemon { span, .. } => span,
+        }
+    }
+
+    // Moves the error along with text an edit has shifted, the open delimiter of a crossed pair too
+    pub(crate) fn shift(&mut self, shift: &Shift) {
+        if let LexerError::CrossedDelimiters { open, .. } = self { shift.span(open); }
+        shift.span(self.span_mut());
+    }
+}
+
+impl From<io::Error> for LexerError {
+    fn from(source: io::Error) -> Self {
+        LexerError::FileIO { source: Arc::new(source), span: Span::default() }
+    }
+}
+
+/* A point in the source: byte offset plus human readable line/column (1 based) */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+/* Half open range of source text, [start, end) */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
 }
 
-pub type Token = TokenType;
+/* Whitespace, newlines and comments between tokens, only kept by a lossless lexer */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia<'a> {
+    pub kind: TriviaKind,
+    pub text: Cow<'a, str>,
+    pub span: Span,
+}
+
+impl Trivia<'_> {
+    pub fn into_owned(self) -> Trivia<'static> {
+        Trivia { kind: self.kind, text: Cow::Owned(self.text.into_owned()), span: self.span }
+    }
+}
+
+/* Leading trivia is everything since the previous token's trailing trivia,
+   which itself runs up to and including the end of its line */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenType<'a>,
+    pub span: Span,
+    pub leading: Vec<Trivia<'a>>,
+    pub trailing: Vec<Trivia<'a>>,
+}
+
+impl<'a> Token<'a> {
+    pub fn new(kind: TokenType<'a>, span: Span) -> Token<'a> {
+        Token { kind, span, leading: Vec::new(), trailing: Vec::new() }
+    }
+
+    /* Detaches the token from the source it borrows from */
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            kind: self.kind.into_owned(),
+            span: self.span,
+            leading: self.leading.into_iter().map(Trivia::into_owned).collect(),
+            trailing: self.trailing.into_iter().map(Trivia::into_owned).collect(),
+        }
+    }
+}
 
 pub struct Delimiters {
     pub raw: char,
     pub kind: DelimitersKind,
 }
 
-#[derive(Debug)]
+/* An opening delimiter waiting for its partner, and where it was opened */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenDelimiter {
+    pub raw: char,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Numeric {
     pub raw: String,
     pub base: NumericBase,
     pub kind: NumericKind,
+    pub suffix: Option<NumericSuffix>,
 }
 
-#[derive(Debug, PartialEq)]
-pub enum TokenType {
+/* Text borrows from the source, and is only owned where escapes had to be decoded */
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenType<'a> {
     /* End of Token Stream */
     EOF,
 
@@ -78,33 +258,102 @@ pub enum TokenType {
     Delimiters{raw: char, kind: DelimitersKind},
 
     /* Operators like '*', '<-', etc.. */
-    Operators(String),
+    Operators(Cow<'a, str>),
 
     /* Sequence of Characters */
-    Identifiers(String),
+    Identifiers(Cow<'a, str>),
+
+    /* Reserved words like 'go', 'when', etc.. */
+    Keywords(KeywordsKind),
 
     /* A single Character */
     Character(char),
 
     /* A group of Numeric Values */
-    Numeric{raw: String, base: NumericBase, kind: NumericKind},
+    Numeric{raw: Cow<'a, str>, base: NumericBase, kind: NumericKind, suffix: Option<NumericSuffix>},
 
     /* A String */
-    String(String),
+    String(Cow<'a, str>),
+
+    /* End of a Statement, '.' */
+    Terminator,
+
+    /* Synthetic, a line more or less indented than the one before in indentation mode */
+    Indent,
+    Dedent,
+
+    /* Source text skipped over while recovering from an error */
+    Error(Cow<'a, str>),
+
+    /* '/// text', documenting whatever follows it */
+    DocComment(Cow<'a, str>),
 
  //   Magic{raw: String, kind: MagicKind, form: MagicForm, component: MagicComponent}
 }
 
+impl TokenType<'_> {
+    pub fn into_owned(self) -> TokenType<'static> {
+        let owned = |text: Cow<str>| Cow::Owned(text.into_owned());
+        match self {
+            TokenType::EOF => TokenType::EOF,
+            TokenType::Delimiters{ raw, kind } => TokenType::Delimiters{ raw, kind },
+            TokenType::Operators(op) => TokenType::Operators(owned(op)),
+            TokenType::Identifiers(name) => TokenType::Identifiers(owned(name)),
+            TokenType::Keywords(keyword) => TokenType::Keywords(keyword),
+            TokenType::Character(c) => TokenType::Character(c),
+            TokenType::Numeric{ raw, base, kind, suffix } => TokenType::Numeric{ raw: owned(raw), base, kind, suffix },
+            TokenType::String(text) => TokenType::String(owned(text)),
+            TokenType::Terminator => TokenType::Terminator,
+            TokenType::Indent => TokenType::Indent,
+            TokenType::Dedent => TokenType::Dedent,
+            TokenType::Error(text) => TokenType::Error(owned(text)),
+            TokenType::DocComment(text) => TokenType::DocComment(owned(text)),
+        }
+    }
+
+    /* Value of a boolean literal, 'true' or 'false' */
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            TokenType::Numeric{ raw, kind: NumericKind::Bool, .. } => Some(raw != "0"),
+            _ => None,
+        }
+    }
+
+    /* Whether `self` is the kind of token `expected` asks for. Delimiters compare by symbol
+       and direction, operators and keywords exactly, anything carrying a value by kind alone */
+    pub fn matches(&self, expected: &TokenType<'_>) -> bool {
+        match (self, expected) {
+            (TokenType::Delimiters{ raw, kind }, TokenType::Delimiters{ raw: other, kind: other_kind }) =>
+                raw == other && std::mem::discriminant(kind) == std::mem::discriminant(other_kind),
+            (TokenType::Operators(op), TokenType::Operators(other)) => op == other,
+            (TokenType::Keywords(keyword), TokenType::Keywords(other)) => keyword == other,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(expected),
+        }
+    }
+}
+
+/* Operators, longest first so the lexer can take the maximal munch */
+pub const OPERATORS: &[&str] = &["-->", "<-", "->", "^", "+", "-", "*", "/", "<", ">", "?"];
+
+/* How a lone '-' is read: closing a parameter list or subtracting */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DashMode {
+    #[default]
+    Auto,
+    Delimiter,
+    Operator,
+}
+
 type ParameterDepthType = i32;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DelimitersKind {
     Opening(ParameterDepthType),
     Closing(ParameterDepthType),
     Seperator,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum NumericKind {
     Any,
     Whole,
@@ -113,7 +362,21 @@ pub enum NumericKind {
     Bool,
 }
 
-#[derive(Debug, PartialEq)]
+/* Storage a literal asks for with a suffix like 'u8', 'i32' or 'f64' */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumericSuffix {
+    pub signedness: Signedness,
+    pub width: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signedness {
+    Signed,
+    Unsigned,
+    Float,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum NumericBase {
     Any,
     Binary,
@@ -121,4 +384,5 @@ pub enum NumericBase {
     Decimal,
     Hexadecimal,
     Base64,
+    Radix(u32),
 }
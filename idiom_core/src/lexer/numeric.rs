@@ -0,0 +1,268 @@
+// Digit alphabets and exact evaluation of numeric literals
+
+use crate::lexer::*;
+
+use num_bigint::{BigInt, BigUint};
+use num_rational::BigRational;
+use num_traits::{Num, One, ToPrimitive, Zero};
+use thiserror::Error;
+
+/* Base64 digits in order of value, 'A' = 0 .. '/' = 63 (RFC 4648) */
+pub const BASE64_DIGITS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/* Characters that end a Base64 literal without belonging to it */
+pub fn ends_base64(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '.' | ')' | ']' | '}' | '~')
+}
+
+pub fn base64_digit(c: char) -> Option<u32> {
+    match c {
+        'A'..='Z' => Some(c as u32 - 'A' as u32),
+        'a'..='z' => Some(c as u32 - 'a' as u32 + 26),
+        '0'..='9' => Some(c as u32 - '0' as u32 + 52),
+        '+' => Some(62),
+        '/' => Some(63),
+        _ => None,
+    }
+}
+
+/* Positional value of a Base64 literal's digits, most significant first */
+pub fn decode_base64(raw: &str) -> Option<BigUint> {
+    if raw.is_empty() {
+        return None;
+    }
+
+    raw.chars().try_fold(BigUint::zero(), |acc, c| Some(acc * 64u32 + base64_digit(c)?))
+}
+
+pub fn encode_base64(value: &BigUint) -> String {
+    let mut digits = Vec::new();
+    let mut rest = value.clone();
+
+    loop {
+        let digit = (&rest % 64u32).iter_u32_digits().next().unwrap_or(0) as usize;
+        digits.push(BASE64_DIGITS.as_bytes()[digit] as char);
+        rest /= 64u32;
+        if rest.is_zero() {
+            break;
+        }
+    }
+
+    digits.iter().rev().collect()
+}
+
+pub const NUMERIC_SUFFIXES: &[(&str, NumericSuffix)] = &[
+    ("u8", NumericSuffix { signedness: Signedness::Unsigned, width: 8 }),
+    ("u16", NumericSuffix { signedness: Signedness::Unsigned, width: 16 }),
+    ("u32", NumericSuffix { signedness: Signedness::Unsigned, width: 32 }),
+    ("u64", NumericSuffix { signedness: Signedness::Unsigned, width: 64 }),
+    ("u128", NumericSuffix { signedness: Signedness::Unsigned, width: 128 }),
+    ("i8", NumericSuffix { signedness: Signedness::Signed, width: 8 }),
+    ("i16", NumericSuffix { signedness: Signedness::Signed, width: 16 }),
+    ("i32", NumericSuffix { signedness: Signedness::Signed, width: 32 }),
+    ("i64", NumericSuffix { signedness: Signedness::Signed, width: 64 }),
+    ("i128", NumericSuffix { signedness: Signedness::Signed, width: 128 }),
+    ("f32", NumericSuffix { signedness: Signedness::Float, width: 32 }),
+    ("f64", NumericSuffix { signedness: Signedness::Float, width: 64 }),
+];
+
+impl std::fmt::Display for NumericSuffix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = match self.signedness {
+            Signedness::Signed => 'i',
+            Signedness::Unsigned => 'u',
+            Signedness::Float => 'f',
+        };
+        write!(f, "{}{}", sign, self.width)
+    }
+}
+
+impl NumericSuffix {
+    /* Whether a literal's value fits the storage, literals themselves are never negative */
+    pub fn can_represent(&self, value: &NumericValue) -> bool {
+        let float_max = if self.width == 32 { f32::MAX as f64 } else { f64::MAX };
+
+        match (self.signedness, value) {
+            (Signedness::Unsigned, NumericValue::Integer(n)) => n.bits() <= self.width as u64,
+            (Signedness::Signed, NumericValue::Integer(n)) => n.bits() < self.width as u64,
+            (Signedness::Float, NumericValue::Integer(n)) => n.to_f64().is_some_and(|f| f <= float_max),
+            (Signedness::Float, NumericValue::Rational(r)) => r.to_f64().is_some_and(|f| f.abs() <= float_max),
+            (Signedness::Float, NumericValue::Float(f)) => f.abs() <= float_max,
+            _ => false,
+        }
+    }
+}
+
+/* Exact value of a numeric literal */
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumericValue {
+    Integer(BigUint),
+    Rational(BigRational),
+    Float(f64),
+    Bool(bool),
+}
+
+/* Problems that still leave a usable value behind */
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum NumericWarning {
+    #[error("Numeric Overflow: {raw:?} is beyond the range of a 64 bit float.")]
+    Overflow { raw: String, span: Span },
+
+    #[error("Precision Lost: {raw:?} is rounded to {value:?}.")]
+    PrecisionLoss { raw: String, value: f64, span: Span },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluated {
+    pub value: NumericValue,
+    pub warnings: Vec<NumericWarning>,
+}
+
+impl NumericBase {
+    /* Named bases for 2, 8, 10, 16 and 64, any other radix from 2 through 36 is kept as is */
+    pub fn from_radix(radix: u32) -> Option<NumericBase> {
+        match radix {
+            2 => Some(NumericBase::Binary),
+            8 => Some(NumericBase::Octal),
+            10 => Some(NumericBase::Decimal),
+            16 => Some(NumericBase::Hexadecimal),
+            64 => Some(NumericBase::Base64),
+            3..=36 => Some(NumericBase::Radix(radix)),
+            _ => None,
+        }
+    }
+
+    pub fn radix(&self) -> u32 {
+        match self {
+            NumericBase::Binary => 2,
+            NumericBase::Octal => 8,
+            NumericBase::Hexadecimal => 16,
+            NumericBase::Base64 => 64,
+            NumericBase::Radix(radix) => *radix,
+            NumericBase::Decimal | NumericBase::Any => 10,
+        }
+    }
+}
+
+fn ten_to(power: u64) -> BigInt {
+    num_traits::pow(BigInt::from(10u32), power as usize)
+}
+
+// Splits a decimal literal into its digits and the power of ten they are scaled by
+fn decimal_parts(raw: &str) -> Option<(BigInt, i64)> {
+    let raw = raw.replace('_', "");
+    let raw = raw.as_str();
+    let (mantissa, exponent) = match raw.find(['e', 'E']) {
+        Some(i) => (&raw[..i], raw[i + 1..].parse::<i64>().ok()?),
+        None => (raw, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", whole, fraction);
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some((BigInt::from_str_radix(&digits, 10).ok()?, exponent.checked_sub(fraction.len() as i64)?))
+}
+
+fn scale(digits: BigInt, power: i64) -> BigRational {
+    if power >= 0 {
+        BigRational::from_integer(digits * ten_to(power.unsigned_abs()))
+    } else {
+        BigRational::new(digits, ten_to(power.unsigned_abs()))
+    }
+}
+
+fn evaluate_float(raw: &str, span: Span) -> Option<Evaluated> {
+    let (digits, power) = decimal_parts(raw)?;
+    let value = raw.replace('_', "").parse::<f64>().ok()?;
+    let mut warnings = Vec::new();
+
+    // decimal exponent of the leading digit, so huge exponents are judged without expanding them
+    let magnitude = power.saturating_add(digits.to_string().len() as i64 - 1);
+
+    if value.is_infinite() {
+        warnings.push(NumericWarning::Overflow { raw: raw.to_string(), span });
+    } else if !digits.is_zero() && (magnitude < -400 || BigRational::from_float(value) != Some(scale(digits, power))) {
+        warnings.push(NumericWarning::PrecisionLoss { raw: raw.to_string(), value, span });
+    }
+
+    Some(Evaluated { value: NumericValue::Float(value), warnings })
+}
+
+/* Turns a numeric token into its exact value: integers for whole numbers of any base,
+   rationals for fractions and floats for exponents */
+pub fn evaluate(token: &Token) -> Result<Evaluated, LexerError> {
+    let (raw, base, kind) = match &token.kind {
+        TokenType::Numeric{ raw, base, kind, .. } => (raw, base, kind),
+        _ => return Err(LexerError::ImproperUsage {
+            expected: TokenType::Numeric{ raw: "<num>".into(), base: NumericBase::Any, kind: NumericKind::Any, suffix: None },
+            found: Box::new(token.clone().into_owned()),
+            span: token.span,
+        }),
+    };
+    let invalid = || LexerError::InvalidNumericLiteral { base: base.clone(), raw: raw.to_string(), received: raw.to_string(), span: token.span };
+
+    let value = match kind {
+        NumericKind::Bool => NumericValue::Bool(raw != "0"),
+        NumericKind::Fractional => {
+            let (digits, power) = decimal_parts(raw).ok_or_else(invalid)?;
+            NumericValue::Rational(scale(digits, power))
+        },
+        NumericKind::Exponential => return evaluate_float(raw, token.span).ok_or_else(invalid),
+        NumericKind::Whole | NumericKind::Any => {
+            let value = match base {
+                NumericBase::Base64 => decode_base64(raw),
+                _ if raw.chars().all(|c| c.is_alphanumeric() || c == '_') => BigUint::from_str_radix(&raw.replace('_', ""), base.radix()).ok(),
+                _ => None,
+            };
+            NumericValue::Integer(value.ok_or_else(invalid)?)
+        },
+    };
+
+    Ok(Evaluated { value, warnings: Vec::new() })
+}
+
+impl NumericValue {
+    /* Writes the value back out as a literal of the given base, None if it has no exact spelling there */
+    pub fn to_literal(&self, base: &NumericBase) -> Option<String> {
+        match self {
+            NumericValue::Bool(b) => Some(b.to_string()),
+            NumericValue::Float(f) if f.is_finite() => Some(format!("{:e}", f)),
+            NumericValue::Float(_) => None,
+            NumericValue::Integer(n) => match base {
+                NumericBase::Decimal | NumericBase::Any => Some(n.to_str_radix(10)),
+                NumericBase::Base64 => Some(format!("64b{}", encode_base64(n))),
+                _ => Some(format!("{}b{}", base.radix(), n.to_str_radix(base.radix()).to_uppercase())),
+            },
+            NumericValue::Rational(r) => {
+                if !matches!(base, NumericBase::Decimal | NumericBase::Any) || r < &BigRational::zero() {
+                    return None;
+                }
+
+                // only denominators made of 2s and 5s terminate in decimal
+                let mut denominator = r.denom().clone();
+                let mut places = 0u64;
+                while !denominator.is_one() {
+                    if (&denominator % 10u32).is_zero() {
+                        denominator /= 10u32;
+                    } else if (&denominator % 2u32).is_zero() {
+                        denominator /= 2u32;
+                    } else if (&denominator % 5u32).is_zero() {
+                        denominator /= 5u32;
+                    } else {
+                        return None;
+                    }
+                    places += 1;
+                }
+
+                let places = places.max(1);
+                let digits = (r * BigRational::from_integer(ten_to(places))).to_integer().to_string();
+                let digits = format!("{:0>width$}", digits, width = places as usize + 1);
+                let (whole, fraction) = digits.split_at(digits.len() - places as usize);
+                Some(format!("{}.{}", whole, fraction))
+            },
+        }
+    }
+}
@@ -0,0 +1,243 @@
+// Lexes from any io::Read a chunk at a time, holding little more than the token being read
+
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::sync::Arc;
+
+use crate::lexer::*;
+
+// Bytes asked of the reader at a time
+pub const READ_CHUNK: usize = 8 * 1024;
+
+// How far past a token the lexer may look to settle it, as in '-->' or '////'
+const LOOKAHEAD: usize = 4;
+
+/* Tokens own their text, since the buffer they were read from is reused. Memory stays
+   around a chunk plus twice the longest token, however long the input runs */
+pub struct ReadLexer<R> {
+    reader: R,
+    config: LexerConfig,
+
+    // Where the text not yet lexed starts, and that text from `origin` on, which may reach
+    // back to the start of the line for the lexer to read its indentation
+    state: LexerState,
+    origin: usize,
+    text: String,
+
+    // The start of a character the last read cut in two
+    partial: Vec<u8>,
+    exhausted: bool,
+
+    // Tokens lexed in one go, handed out one at a time
+    ready: VecDeque<Result<Token<'static>, LexerError>>,
+    diagnostics: Vec<LexerError>,
+
+    // An I/O error held back until the tokens settled before it are handed out
+    failure: Option<LexerError>,
+
+    // Set once iteration has handed out EOF or an I/O error
+    finished: bool,
+}
+
+impl<R: Read> ReadLexer<R> {
+    pub fn new(reader: R) -> ReadLexer<R> {
+        ReadLexer::with_config(reader, LexerConfig::default())
+    }
+
+    pub fn with_config(reader: R, config: LexerConfig) -> ReadLexer<R> {
+        let state = Lexer::with_config("", config.clone()).snapshot();
+        ReadLexer {
+            reader,
+            config,
+            state,
+            origin: 0,
+            text: String::new(),
+            partial: Vec::new(),
+            exhausted: false,
+            ready: VecDeque::new(),
+            diagnostics: Vec::new(),
+            failure: None,
+            finished: false,
+        }
+    }
+
+    pub fn config(&self) -> &LexerConfig {
+        &self.config
+    }
+
+    // Settings are carried in the lexer state, so they go through a lexer to keep its rules
+    fn adjust(&mut self, change: impl FnOnce(&mut Lexer)) {
+        let mut lexer = Lexer::resume("", self.origin, self.config.clone(), self.state.clone());
+        change(&mut lexer);
+        self.state = lexer.snapshot();
+    }
+
+    pub fn set_recovery(&mut self, recovering: bool) {
+        self.adjust(|lexer| lexer.set_recovery(recovering));
+    }
+
+    pub fn set_lossless(&mut self, lossless: bool) {
+        self.adjust(|lexer| lexer.set_lossless(lossless));
+    }
+
+    pub fn diagnostics(&self) -> &[LexerError] {
+        &self.diagnostics
+    }
+
+    pub fn take_diagnostics(&mut self) -> Vec<LexerError> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    // Bytes held in memory, read but not yet lexed
+    pub fn buffered(&self) -> usize {
+        self.text.len() + self.partial.len()
+    }
+
+    pub fn next_token(&mut self) -> Result<Token<'static>, LexerError> {
+        loop {
+            if let Some(token) = self.ready.pop_front() {
+                return token;
+            }
+            self.lex()?;
+        }
+    }
+
+    /* Lexes every token it can be sure of. When the text read so far ends too soon to be
+       sure of any, more is read until what is left to lex has at least doubled, so a long
+       token is lexed again only a few times however many chunks it spans */
+    fn lex(&mut self) -> Result<(), LexerError> {
+        if let Some(err) = self.failure.take() {
+            return Err(err);
+        }
+
+        let mut wanted = 0;
+        loop {
+            while !self.exhausted && self.pending() < wanted {
+                if let Err(err) = self.read() {
+                    if !self.lex_settled() {
+                        return Err(err);
+                    }
+                    self.failure = Some(err);
+                    return Ok(());
+                }
+            }
+            if self.lex_settled() {
+                return Ok(());
+            }
+            wanted = self.pending() * 2 + 1;
+        }
+    }
+
+    /* Lexes on from the state so far with one lexer, keeping everything up to the last
+       point it can stop at that is settled, then lets go of the text behind it */
+    fn lex_settled(&mut self) -> bool {
+        let mut lexer = Lexer::resume(&self.text, self.origin, self.config.clone(), self.state.clone());
+        let mut settled = false;
+
+        loop {
+            let kept = self.ready.len();
+            let end = loop {
+                let token = lexer.next_token();
+                let end = matches!(token, Ok(Token { kind: TokenType::EOF, .. }));
+                self.ready.push_back(token.map(Token::into_owned));
+                if end || lexer.at_boundary() {
+                    break end;
+                }
+            };
+
+            let consumed = lexer.location().offset - self.origin;
+            if !self.exhausted && !self.settled(consumed) {
+                self.ready.truncate(kept);
+                break;
+            }
+
+            self.diagnostics.extend(lexer.take_diagnostics());
+            self.state = lexer.snapshot();
+            settled = true;
+            if end {
+                break;
+            }
+        }
+
+        if settled {
+            let keep = self.state.lookback();
+            self.text.drain(..keep - self.origin);
+            self.origin = keep;
+        }
+        settled
+    }
+
+    // Bytes read past the state lexing carries on from
+    fn pending(&self) -> usize {
+        self.origin + self.text.len() - self.state.position().offset
+    }
+
+    // Whether the text left after `consumed` is enough for the lexer not to have missed anything
+    fn settled(&self, consumed: usize) -> bool {
+        let rest = self.text.get(consumed..).unwrap_or_default();
+        rest.len() >= LOOKAHEAD && rest.contains(|c| c != ' ' && c != '\t')
+    }
+
+    // Appends the next chunk, keeping back the start of any character it cuts in two
+    fn read(&mut self) -> Result<(), LexerError> {
+        let mut chunk = [0; READ_CHUNK];
+        let read = loop {
+            match self.reader.read(&mut chunk) {
+                Ok(read) => break read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(self.io_error(err)),
+            }
+        };
+
+        // the text is never taken as complete when it ends partway through a character
+        if read == 0 {
+            if !self.partial.is_empty() {
+                return Err(self.io_error(io::Error::new(io::ErrorKind::UnexpectedEof, "input ends partway through a UTF-8 character")));
+            }
+            self.exhausted = true;
+            return Ok(());
+        }
+
+        self.partial.extend_from_slice(&chunk[..read]);
+        let (valid, invalid) = match std::str::from_utf8(&self.partial) {
+            Ok(_) => (self.partial.len(), false),
+            Err(err) => (err.valid_up_to(), err.error_len().is_some()),
+        };
+        self.text += &String::from_utf8_lossy(&self.partial[..valid]);
+        self.partial.drain(..valid);
+
+        match invalid {
+            true => Err(self.io_error(io::Error::new(io::ErrorKind::InvalidData, "input is not valid UTF-8"))),
+            false => Ok(()),
+        }
+    }
+
+    // Reported at the end of the text read so far, which is where the reader gave out
+    fn io_error(&self, source: io::Error) -> LexerError {
+        let mut at = self.state.position();
+        for c in self.text[at.offset - self.origin..].chars() {
+            at.offset += c.len_utf8();
+            at.col += 1;
+            if c == '\n' {
+                at.line += 1;
+                at.col = 1;
+            }
+        }
+        LexerError::FileIO { source: Arc::new(source), span: Span { start: at, end: at } }
+    }
+}
+
+// Yields every token up to and including EOF, stopping early at an I/O error
+impl<R: Read> Iterator for ReadLexer<R> {
+    type Item = Result<Token<'static>, LexerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let token = self.next_token();
+        self.finished = matches!(token, Ok(Token { kind: TokenType::EOF, .. }) | Err(LexerError::FileIO { .. }));
+        Some(token)
+    }
+}
@@ -0,0 +1,85 @@
+// Buffered view over a Lexer for parsers that need to look ahead or backtrack
+
+use crate::lexer::*;
+
+// A position in the stream to rewind to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mark(usize);
+
+#[derive(Debug, Clone)]
+pub struct TokenStream<'a> {
+    lexer: Lexer<'a>,
+
+    // Everything lexed so far, kept so any mark can be rewound to
+    buffer: Vec<Result<Token<'a>, LexerError>>,
+    cursor: usize,
+}
+
+impl<'a> From<Lexer<'a>> for TokenStream<'a> {
+    fn from(lexer: Lexer<'a>) -> TokenStream<'a> {
+        TokenStream::new(lexer)
+    }
+}
+
+impl<'a> TokenStream<'a> {
+    pub fn new(lexer: Lexer<'a>) -> TokenStream<'a> {
+        TokenStream { lexer, buffer: Vec::new(), cursor: 0 }
+    }
+
+    pub fn lexer(&self) -> &Lexer<'a> {
+        &self.lexer
+    }
+
+    /* Lexes until `index` is buffered, or the lexer has run out. A lexer already iterated
+       to its end hands out nothing more, so the stream answers EOF where it stopped */
+    fn fill(&mut self, index: usize) {
+        while self.buffer.len() <= index {
+            match self.lexer.next() {
+                Some(token) => self.buffer.push(token),
+                None if self.buffer.is_empty() => {
+                    let at = self.lexer.location();
+                    self.buffer.push(Ok(Token::new(TokenType::EOF, Span { start: at, end: at })));
+                },
+                None => break,
+            }
+        }
+    }
+
+    /* The token `k` places past the cursor without consuming anything,
+       looking past the end keeps answering EOF */
+    pub fn peek_nth(&mut self, k: usize) -> &Result<Token<'a>, LexerError> {
+        self.fill(self.cursor + k);
+        let index = (self.cursor + k).min(self.buffer.len() - 1);
+        &self.buffer[index]
+    }
+
+    pub fn peek(&mut self) -> &Result<Token<'a>, LexerError> {
+        self.peek_nth(0)
+    }
+
+    pub fn next_token(&mut self) -> Result<Token<'a>, LexerError> {
+        let token = self.peek().clone();
+        self.cursor = (self.cursor + 1).min(self.buffer.len());
+        token
+    }
+
+    pub fn mark(&self) -> Mark {
+        Mark(self.cursor)
+    }
+
+    pub fn rewind(&mut self, mark: Mark) {
+        self.cursor = mark.0;
+    }
+
+    /* Consumes the next token if it matches `expected` (see TokenType::matches),
+       otherwise leaves it in place and reports what was found instead */
+    pub fn expect(&mut self, expected: TokenType<'static>) -> Result<Token<'a>, LexerError> {
+        let found = self.peek().clone()?;
+
+        if found.kind.matches(&expected) {
+            self.next_token()
+        } else {
+            Err(LexerError::ImproperUsage { expected, span: found.span, found: Box::new(found.into_owned()) })
+        }
+    }
+}
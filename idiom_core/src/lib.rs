@@ -1,3 +1,7 @@
 pub mod lexer;
 
 pub use lexer::*;
+
+pub mod parser;
+
+pub use parser::*;
@@ -0,0 +1,76 @@
+// Syntax tree shared by every dialect, spans and spelling are left behind in the tokens
+
+use crate::lexer::*;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program {
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Function(Function),
+
+    // Comments are kept as written, markers included
+    Comment(String),
+
+    // A comment ending the line of what comes before it
+    TrailingComment(String),
+
+    // An empty line, kept so the layout survives a rewrite
+    BlankLine,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub docs: Vec<String>,
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /* 'dec name' */
+    Declare(String),
+
+    /* '^ value' */
+    Return(Expr),
+
+    /* 'when' followed by any number of 'or when', and possibly an 'or else' */
+    When(Vec<Branch>),
+
+    Expr(Expr),
+
+    Comment(String),
+    TrailingComment(String),
+    BlankLine,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Branch {
+    // Comments and blank lines between the branch before and this one
+    pub leading: Vec<Statement>,
+
+    // None for 'or else'
+    pub condition: Option<Expr>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Identifier(String),
+
+    /* Numeric, String and Character tokens, and null */
+    Literal(TokenType<'static>),
+
+    /* '-->' lowers to '->', the dash only being there to close the parameters */
+    Binary { op: String, lhs: Box<Expr>, rhs: Box<Expr> },
+    Postfix { op: String, operand: Box<Expr> },
+
+    Call { name: String, args: Vec<Expr> },
+    Group(Box<Expr>),
+
+    /* Values separated by ',', which only .etu has */
+    List(Vec<Expr>),
+}
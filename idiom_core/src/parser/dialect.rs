@@ -0,0 +1,69 @@
+// The two surface syntaxes, both lowering to the same syntax tree
+
+use std::path::Path;
+
+use crate::lexer::*;
+
+/* .idi: 'go name, params - | body ~', parameters juxtaposed
+   .etu: 'do name: params -' with an indented body and ',' between values */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    #[default]
+    Idi,
+    Etu,
+}
+
+impl Dialect {
+    pub fn from_name(name: &str) -> Option<Dialect> {
+        match name {
+            "idi" => Some(Dialect::Idi),
+            "etu" => Some(Dialect::Etu),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dialect::Idi => "idi",
+            Dialect::Etu => "etu",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Dialect> {
+        path.extension().and_then(|ext| ext.to_str()).and_then(Dialect::from_name)
+    }
+
+    // '//! dialect: etu' on the first line that isn't blank
+    pub fn from_pragma(source: &str) -> Option<Dialect> {
+        let line = source.lines().map(str::trim).find(|line| !line.is_empty())?;
+        let name = line.strip_prefix("//!")?.trim().strip_prefix("dialect:")?;
+        Dialect::from_name(name.trim())
+    }
+
+    // A pragma outranks the file extension, .idi is assumed when neither says
+    pub fn detect(path: Option<&Path>, source: &str) -> Dialect {
+        Dialect::from_pragma(source)
+            .or_else(|| path.and_then(Dialect::from_path))
+            .unwrap_or_default()
+    }
+
+    pub fn config(&self) -> LexerConfig {
+        match self {
+            Dialect::Idi => LexerConfig::default(),
+            Dialect::Etu => LexerConfig::default()
+                .without_role(DelimiterRole::FunctionBody)
+                .with_pair(':', '-', DelimiterRole::Parameters)
+                .with_separator(',')
+                .with_indentation(true)
+                .with_implicit_parameter_close(true),
+        }
+    }
+
+    // Keyword introducing a function
+    pub fn function_keyword(&self) -> KeywordsKind {
+        match self {
+            Dialect::Idi => KeywordsKind::Go,
+            Dialect::Etu => KeywordsKind::Do,
+        }
+    }
+}
@@ -0,0 +1,12 @@
+#[allow(clippy::module_inception)]
+pub mod parser;
+pub use parser::*;
+
+pub mod ast;
+pub use ast::*;
+
+pub mod dialect;
+pub use dialect::*;
+
+pub mod translate;
+pub use translate::*;
@@ -0,0 +1,535 @@
+use crate::lexer::*;
+use crate::parser::*;
+
+/* Operators by how tightly they bind, loosest first. '<-' and '->' sit below all of
+   them and are handled on their own, being right and left associative respectively */
+const PRECEDENCE: &[&[&str]] = &[&["<", ">"], &["+", "-"], &["*", "/"]];
+
+/* How deep brackets, calls and blocks may nest. Each level costs several stack frames,
+   so past this a parse fails rather than overflowing the stack */
+pub const MAX_DEPTH: usize = 64;
+
+// Comments and blank lines met between tokens, waiting to be placed in the tree
+enum Aside {
+    Comment(String),
+    Trailing(String),
+    Blank,
+}
+
+impl From<Aside> for Item {
+    fn from(aside: Aside) -> Item {
+        match aside {
+            Aside::Comment(text) => Item::Comment(text),
+            Aside::Trailing(text) => Item::TrailingComment(text),
+            Aside::Blank => Item::BlankLine,
+        }
+    }
+}
+
+impl From<Aside> for Statement {
+    fn from(aside: Aside) -> Statement {
+        match aside {
+            Aside::Comment(text) => Statement::Comment(text),
+            Aside::Trailing(text) => Statement::TrailingComment(text),
+            Aside::Blank => Statement::BlankLine,
+        }
+    }
+}
+
+// Recursive descent over a TokenStream, dialects differ only in what the delimiters' roles are spelled as
+pub struct Parser<'a> {
+    tokens: TokenStream<'a>,
+    dialect: Dialect,
+
+    // Comments and blank lines read past but not yet placed in the tree, each with where it starts,
+    // and how far into the source they have been gathered
+    asides: Vec<(usize, Aside)>,
+    gathered: usize,
+
+    // Where the last token read ends
+    read: usize,
+
+    // Whether the last token read ended its line
+    fresh: bool,
+
+    // Levels of nesting the parser is currently inside
+    depth: usize,
+}
+
+// Parses a whole source file written in `dialect`
+pub fn parse(source: &str, dialect: Dialect) -> Result<Program, LexerError> {
+    Parser::new(source, dialect).parse()
+}
+
+// A doc comment written back out as the line it was read from
+pub(crate) fn doc_line(doc: &str) -> String {
+    format!("/// {}", doc).trim_end().to_string()
+}
+
+impl<'a> Parser<'a> {
+    // The lexer runs lossless so comments can be carried into the tree
+    pub fn new(source: &'a str, dialect: Dialect) -> Parser<'a> {
+        let mut lexer = Lexer::with_config(source, dialect.config());
+        lexer.set_lossless(true);
+
+        Parser { tokens: TokenStream::new(lexer), dialect, asides: Vec::new(), gathered: 0, read: 0, fresh: true, depth: 0 }
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    fn peek(&mut self) -> Result<Token<'a>, LexerError> {
+        self.tokens.peek().clone()
+    }
+
+    fn advance(&mut self) -> Result<Token<'a>, LexerError> {
+        let token = self.tokens.next_token()?;
+        self.gather(&token, true);
+        self.read = token.span.end.offset;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: TokenType<'static>) -> Result<Token<'a>, LexerError> {
+        let token = self.tokens.expect(expected)?;
+        self.gather(&token, true);
+        self.read = token.span.end.offset;
+        Ok(token)
+    }
+
+    /* A comment is trailing when something precedes it on its line, and a newline with
+       only whitespace before it ends a blank line. Block comments after a token are left
+       to the next token's leading trivia, so whether that starts a line is carried over */
+    fn gather(&mut self, token: &Token<'a>, trailing: bool) {
+        let mut fresh = self.fresh;
+        for trivia in &token.leading {
+            if trivia.span.start.offset >= self.gathered {
+                let aside = match trivia.kind {
+                    TriviaKind::LineComment | TriviaKind::BlockComment if fresh => Some(Aside::Comment(trivia.text.to_string())),
+                    TriviaKind::LineComment | TriviaKind::BlockComment => Some(Aside::Trailing(trivia.text.to_string())),
+                    TriviaKind::Newline if fresh => Some(Aside::Blank),
+                    _ => None,
+                };
+                self.asides.extend(aside.map(|aside| (trivia.span.start.offset, aside)));
+                self.gathered = trivia.span.end.offset;
+            }
+            fresh = match trivia.kind {
+                TriviaKind::Newline => true,
+                TriviaKind::Whitespace => fresh,
+                _ => false,
+            };
+        }
+
+        if !trailing {
+            return;
+        }
+        for trivia in &token.trailing {
+            if trivia.span.start.offset >= self.gathered && matches!(trivia.kind, TriviaKind::LineComment | TriviaKind::BlockComment) {
+                self.asides.push((trivia.span.start.offset, Aside::Trailing(trivia.text.to_string())));
+                self.gathered = trivia.span.end.offset;
+            }
+        }
+
+        // tokens the lexer makes up, like Indent, take up no text and leave the line as it was
+        self.fresh = match token.trailing.last() {
+            Some(trivia) => trivia.kind == TriviaKind::Newline,
+            None if token.span.start == token.span.end => fresh,
+            None => false,
+        };
+    }
+
+    // Comments and blank lines up to the next token, which are placed ahead of whatever it starts
+    fn take_asides(&mut self) -> Result<Vec<Aside>, LexerError> {
+        self.gather_ahead()?;
+        Ok(std::mem::take(&mut self.asides).into_iter().map(|(_, aside)| aside).collect())
+    }
+
+    /* Gathers what take_asides would take without taking it. Tokens the lexer makes up, like
+       Dedent, come before a comment trailing the line they end, which is taken along so it
+       stays with what precedes it */
+    fn gather_ahead(&mut self) -> Result<(), LexerError> {
+        let token = self.peek()?;
+        self.gather(&token, false);
+
+        let mut ahead = 1;
+        while !self.fresh && matches!(self.tokens.peek_nth(ahead - 1), Ok(token) if token.span.start == token.span.end && token.kind != TokenType::EOF) {
+            let token = match self.tokens.peek_nth(ahead) {
+                Ok(token) => token.clone(),
+                Err(_) => break,
+            };
+            let line = token.leading.iter().take_while(|trivia| trivia.kind != TriviaKind::Newline);
+            for trivia in line {
+                if trivia.span.start.offset >= self.gathered && matches!(trivia.kind, TriviaKind::LineComment | TriviaKind::BlockComment) {
+                    self.asides.push((trivia.span.start.offset, Aside::Trailing(trivia.text.to_string())));
+                    self.gathered = trivia.span.end.offset;
+                }
+            }
+            ahead += 1;
+        }
+        Ok(())
+    }
+
+    /* Comments read inside what was parsed since `start`, which is written out on one line,
+       so they go on lines of their own ahead of it. Blank lines within it are dropped */
+    fn asides_within(&mut self, start: usize) -> Vec<Statement> {
+        let within = self.asides.iter().take_while(|(at, _)| *at < self.read).count();
+        self.asides.drain(..within)
+            .filter(|(at, _)| *at >= start)
+            .filter_map(|(_, aside)| match aside {
+                Aside::Comment(text) | Aside::Trailing(text) => Some(Statement::Comment(text)),
+                Aside::Blank => None,
+            })
+            .collect()
+    }
+
+    fn unexpected(expected: TokenType<'static>, found: Token) -> LexerError {
+        LexerError::ImproperUsage { expected, span: found.span, found: Box::new(found.into_owned()) }
+    }
+
+    // Runs `parse` one level deeper, failing at the next token once MAX_DEPTH is reached
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, LexerError>) -> Result<T, LexerError> {
+        if self.depth >= MAX_DEPTH {
+            let span = match self.tokens.peek() {
+                Ok(token) => token.span,
+                Err(err) => err.span(),
+            };
+            return Err(LexerError::NestedTooDeeply { limit: MAX_DEPTH, span });
+        }
+
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    // Role of a delimiter token, and whether it opens
+    fn role(&self, kind: &TokenType) -> Option<(DelimiterRole, bool)> {
+        let config = self.tokens.lexer().config();
+        match kind {
+            TokenType::Delimiters{ raw, kind: DelimitersKind::Opening(_) } => config.opened_by(*raw).map(|pair| (pair.role, true)),
+            TokenType::Delimiters{ raw, kind: DelimitersKind::Closing(_) } => config.closed_by(*raw).map(|pair| (pair.role, false)),
+            _ => None,
+        }
+    }
+
+    fn at(&mut self, role: DelimiterRole, opening: bool) -> Result<bool, LexerError> {
+        let token = self.peek()?;
+        Ok(self.role(&token.kind) == Some((role, opening)))
+    }
+
+    // The token a role would be spelled with, for error messages
+    fn spelled(&self, role: DelimiterRole, opening: bool) -> TokenType<'static> {
+        let pair = self.tokens.lexer().config().role(role).copied();
+        match (pair, opening) {
+            (Some(pair), true) => TokenType::Delimiters{ raw: pair.open, kind: DelimitersKind::Opening(0) },
+            (Some(pair), false) => TokenType::Delimiters{ raw: pair.close, kind: DelimitersKind::Closing(0) },
+            (None, true) => TokenType::Indent,
+            (None, false) => TokenType::Dedent,
+        }
+    }
+
+    fn expect_role(&mut self, role: DelimiterRole, opening: bool) -> Result<Token<'a>, LexerError> {
+        let token = self.advance()?;
+        match self.role(&token.kind) {
+            Some(found) if found == (role, opening) => Ok(token),
+            _ => Err(Parser::unexpected(self.spelled(role, opening), token)),
+        }
+    }
+
+    fn is_separator(kind: &TokenType) -> bool {
+        matches!(kind, TokenType::Delimiters{ kind: DelimitersKind::Seperator, .. })
+    }
+
+    // Lexer errors outrank whatever the parser made of the Error tokens they left behind
+    pub fn parse(&mut self) -> Result<Program, LexerError> {
+        let program = self.program();
+        match self.tokens.lexer().diagnostics().first() {
+            Some(err) => Err(err.clone()),
+            None => program,
+        }
+    }
+
+    // Doc comments with no function after them to document are kept as plain comments
+    fn program(&mut self) -> Result<Program, LexerError> {
+        let mut program = Program::default();
+        loop {
+            program.items.extend(self.take_asides()?.into_iter().map(Item::from));
+            let mut docs = self.docs()?;
+            let token = self.peek()?;
+            if token.kind != TokenType::Keywords(self.dialect.function_keyword()) {
+                program.items.extend(docs.drain(..).map(|doc| Item::Comment(doc_line(&doc))));
+            }
+
+            match token.kind {
+                TokenType::EOF => return Ok(program),
+                TokenType::Terminator => { self.advance()?; },
+                _ => program.items.push(Item::Function(self.function(docs)?)),
+            }
+        }
+    }
+
+    fn docs(&mut self) -> Result<Vec<String>, LexerError> {
+        let mut docs = Vec::new();
+        while let TokenType::DocComment(doc) = self.peek()?.kind {
+            self.advance()?;
+            docs.push(doc.into_owned());
+        }
+        Ok(docs)
+    }
+
+    // 'go name, params - | body ~' or 'do name: params -' and an indented body
+    fn function(&mut self, docs: Vec<String>) -> Result<Function, LexerError> {
+        self.expect(TokenType::Keywords(self.dialect.function_keyword()))?;
+
+        let token = self.advance()?;
+        let name = match token.kind {
+            TokenType::Identifiers(name) => name.into_owned(),
+            TokenType::Keywords(KeywordsKind::Entry) => KeywordsKind::Entry.as_str().to_string(),
+            _ => return Err(Parser::unexpected(TokenType::Identifiers("<name>".into()), token)),
+        };
+
+        let params = self.params()?;
+        let body = self.block()?;
+        Ok(Function { docs, name, params, body })
+    }
+
+    fn params(&mut self) -> Result<Vec<String>, LexerError> {
+        // without parameters a .etu header still ends on a '-', possibly after a stray ','
+        if !self.at(DelimiterRole::Parameters, true)? {
+            if Parser::is_separator(&self.peek()?.kind) {
+                self.advance()?;
+            }
+            self.expect(TokenType::Operators("-".into()))?;
+            return Ok(Vec::new());
+        }
+
+        self.advance()?;
+        let mut params = Vec::new();
+        loop {
+            let token = self.advance()?;
+            match token.kind {
+                TokenType::Identifiers(param) => params.push(param.into_owned()),
+                ref kind if Parser::is_separator(kind) => {},
+                ref kind if self.role(kind) == Some((DelimiterRole::Parameters, false)) => return Ok(params),
+                _ => return Err(Parser::unexpected(TokenType::Identifiers("<parameter>".into()), token)),
+            }
+        }
+    }
+
+    fn closes_block(&self, kind: &TokenType, indented: bool) -> bool {
+        match indented {
+            true => *kind == TokenType::Dedent,
+            false => self.role(kind) == Some((DelimiterRole::FunctionBody, false)),
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>, LexerError> {
+        self.nested(Parser::statements)
+    }
+
+    // '| statements ~' or an indented run of statements
+    fn statements(&mut self) -> Result<Vec<Statement>, LexerError> {
+        let indented = self.peek()?.kind == TokenType::Indent;
+        if indented {
+            self.advance()?;
+        } else {
+            self.expect_role(DelimiterRole::FunctionBody, true)?;
+        }
+
+        let mut statements = Vec::new();
+        loop {
+            statements.extend(self.take_asides()?.into_iter().map(Statement::from));
+            let token = self.peek()?;
+            if self.closes_block(&token.kind, indented) {
+                self.advance()?;
+                return Ok(statements);
+            }
+
+            match token.kind {
+                TokenType::Terminator => { self.advance()?; continue; },
+                TokenType::DocComment(doc) => {
+                    self.advance()?;
+                    statements.push(Statement::Comment(doc_line(&doc)));
+                    continue;
+                },
+                TokenType::EOF => return Err(Parser::unexpected(self.spelled(DelimiterRole::FunctionBody, false), token)),
+                _ => {},
+            }
+
+            let start = self.peek()?.span.start.offset;
+            let statement = self.statement()?;
+            let closed = matches!(statement, Statement::When(_));
+            statements.extend(self.asides_within(start));
+            statements.push(statement);
+
+            // every statement but the last ends with a terminator, 'when' ends with its own block
+            let token = self.peek()?;
+            if token.kind == TokenType::Terminator {
+                self.advance()?;
+            } else if !closed && !self.closes_block(&token.kind, indented) {
+                return Err(Parser::unexpected(TokenType::Terminator, token));
+            }
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, LexerError> {
+        let token = self.peek()?;
+        match token.kind {
+            TokenType::Keywords(KeywordsKind::Dec) => {
+                self.advance()?;
+                let token = self.advance()?;
+                match token.kind {
+                    TokenType::Identifiers(name) => Ok(Statement::Declare(name.into_owned())),
+                    _ => Err(Parser::unexpected(TokenType::Identifiers("<name>".into()), token)),
+                }
+            },
+            TokenType::Operators(ref op) if op == "^" => {
+                self.advance()?;
+                Ok(Statement::Return(self.expr()?))
+            },
+            TokenType::Keywords(KeywordsKind::When) => self.when(),
+            _ => Ok(Statement::Expr(self.expr()?)),
+        }
+    }
+
+    /* 'when, condition - | ... ~' then any 'or when' and a final 'or else'. What comes between
+       two branches stays ahead of the second, what follows the last is left to the caller */
+    fn when(&mut self) -> Result<Statement, LexerError> {
+        self.advance()?;
+        let mut branches = vec![Branch { leading: Vec::new(), condition: Some(self.condition()?), body: self.block()? }];
+
+        loop {
+            self.gather_ahead()?;
+            if self.peek()?.kind != TokenType::Keywords(KeywordsKind::Or) {
+                break;
+            }
+            let leading = self.take_asides()?.into_iter().map(Statement::from).collect();
+
+            self.advance()?;
+            let token = self.advance()?;
+            match token.kind {
+                TokenType::Keywords(KeywordsKind::When) => {
+                    branches.push(Branch { leading, condition: Some(self.condition()?), body: self.block()? });
+                },
+                TokenType::Keywords(KeywordsKind::Else) => {
+                    branches.push(Branch { leading, condition: None, body: self.block()? });
+                    break;
+                },
+                _ => return Err(Parser::unexpected(TokenType::Keywords(KeywordsKind::When), token)),
+            }
+        }
+        Ok(Statement::When(branches))
+    }
+
+    fn condition(&mut self) -> Result<Expr, LexerError> {
+        self.expect_role(DelimiterRole::Parameters, true)?;
+        let condition = self.expr()?;
+        self.expect_role(DelimiterRole::Parameters, false)?;
+        Ok(condition)
+    }
+
+    pub fn expr(&mut self) -> Result<Expr, LexerError> {
+        self.nested(Parser::assignment)
+    }
+
+    fn assignment(&mut self) -> Result<Expr, LexerError> {
+        let lhs = self.list()?;
+
+        let mut lhs = match self.peek()?.kind {
+            TokenType::Operators(op) if op == "<-" => {
+                self.advance()?;
+                return Ok(Expr::Binary { op: op.into_owned(), lhs: Box::new(lhs), rhs: Box::new(self.expr()?) });
+            },
+            _ => lhs,
+        };
+
+        while let TokenType::Operators(op) = self.peek()?.kind {
+            if op != "->" && op != "-->" {
+                break;
+            }
+            self.advance()?;
+            lhs = Expr::Binary { op: "->".to_string(), lhs: Box::new(lhs), rhs: Box::new(self.list()?) };
+        }
+        Ok(lhs)
+    }
+
+    fn list(&mut self) -> Result<Expr, LexerError> {
+        let first = self.binary(0)?;
+        if !Parser::is_separator(&self.peek()?.kind) {
+            return Ok(first);
+        }
+
+        let mut values = vec![first];
+        while Parser::is_separator(&self.peek()?.kind) {
+            self.advance()?;
+            values.push(self.binary(0)?);
+        }
+        Ok(Expr::List(values))
+    }
+
+    // Operators binding at least as tightly as `level`, climbing rather than recursing per level
+    fn binary(&mut self, level: usize) -> Result<Expr, LexerError> {
+        let mut lhs = self.postfix()?;
+        while let TokenType::Operators(op) = self.peek()?.kind {
+            let binds = match PRECEDENCE.iter().position(|ops| ops.contains(&op.as_ref())) {
+                Some(binds) if binds >= level => binds,
+                _ => break,
+            };
+            self.advance()?;
+            lhs = Expr::Binary { op: op.into_owned(), lhs: Box::new(lhs), rhs: Box::new(self.binary(binds + 1)?) };
+        }
+        Ok(lhs)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, LexerError> {
+        let mut operand = self.primary()?;
+        while let TokenType::Operators(op) = self.peek()?.kind {
+            if op != "?" {
+                break;
+            }
+            self.advance()?;
+            operand = Expr::Postfix { op: op.into_owned(), operand: Box::new(operand) };
+        }
+        Ok(operand)
+    }
+
+    fn primary(&mut self) -> Result<Expr, LexerError> {
+        let token = self.advance()?;
+        match token.kind {
+            TokenType::Identifiers(name) if self.at(DelimiterRole::Parameters, true)? => self.call(name.into_owned()),
+            TokenType::Identifiers(name) => Ok(Expr::Identifier(name.into_owned())),
+            TokenType::Numeric{..} | TokenType::String(_) | TokenType::Character(_) | TokenType::Keywords(KeywordsKind::Null) => Ok(Expr::Literal(token.kind.into_owned())),
+            ref kind if self.role(kind) == Some((DelimiterRole::Join, true)) => {
+                let inner = self.expr()?;
+                self.expect_role(DelimiterRole::Join, false)?;
+                Ok(Expr::Group(Box::new(inner)))
+            },
+            _ => Err(Parser::unexpected(TokenType::Identifiers("<expression>".into()), token)),
+        }
+    }
+
+    /* 'name, a b -' or 'name: a, b -'. A '-->' or the bracket around the call may close
+       the arguments instead, in which case the closer is left for the caller */
+    fn call(&mut self, name: String) -> Result<Expr, LexerError> {
+        self.nested(|parser| parser.arguments(name))
+    }
+
+    fn arguments(&mut self, name: String) -> Result<Expr, LexerError> {
+        self.advance()?;
+        let mut args = Vec::new();
+        loop {
+            let token = self.peek()?;
+            match token.kind {
+                ref kind if self.role(kind) == Some((DelimiterRole::Parameters, false)) => {
+                    self.advance()?;
+                    break;
+                },
+                ref kind if Parser::is_separator(kind) => { self.advance()?; },
+                TokenType::Operators(ref op) if op == "-->" => break,
+                TokenType::Delimiters{ kind: DelimitersKind::Closing(_), .. } | TokenType::Terminator | TokenType::Dedent | TokenType::EOF => break,
+                _ => args.push(self.binary(0)?),
+            }
+        }
+        Ok(Expr::Call { name, args })
+    }
+}
@@ -0,0 +1,245 @@
+// Writes a syntax tree back out as source in either dialect
+
+use crate::lexer::*;
+use crate::parser::*;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Translation {
+    pub text: String,
+
+    // Constructs the target dialect cannot express, written out as closely as it allows
+    pub unsupported: Vec<String>,
+}
+
+pub fn translate(source: &str, from: Dialect, to: Dialect) -> Result<Translation, LexerError> {
+    Ok(print(&parse(source, from)?, to))
+}
+
+pub fn print(program: &Program, dialect: Dialect) -> Translation {
+    let mut printer = Printer { dialect, function: String::new(), out: Translation::default(), commented: false };
+    printer.program(program);
+    printer.out
+}
+
+struct Printer {
+    dialect: Dialect,
+
+    // Function being written, so the report can say where
+    function: String,
+    out: Translation,
+
+    // Whether the last line written ends in a '//' comment, which would swallow anything added to it
+    commented: bool,
+}
+
+impl Printer {
+    fn line(&mut self, depth: usize, text: &str) {
+        let indent = match self.dialect {
+            Dialect::Idi => "    ",
+            Dialect::Etu => "  ",
+        };
+        self.out.text += &indent.repeat(depth);
+        self.out.text += text;
+        self.out.text.push('\n');
+        self.commented = false;
+    }
+
+    // Ends the line last written with `text`, or gives it a line of its own where it can't
+    fn trailing(&mut self, depth: usize, text: &str) {
+        if self.commented || !self.out.text.ends_with('\n') {
+            return self.comment(depth, text);
+        }
+        self.out.text.pop();
+        self.out.text += " ";
+        self.out.text += text;
+        self.out.text.push('\n');
+        self.commented = text.starts_with("//");
+    }
+
+    fn blank(&mut self) {
+        self.out.text.push('\n');
+        self.commented = false;
+    }
+
+    fn unsupported(&mut self, construct: &str) {
+        let note = format!("{} in '{}' has no .{} equivalent", construct, self.function, self.dialect.name());
+        if !self.out.unsupported.contains(&note) {
+            self.out.unsupported.push(note);
+        }
+    }
+
+    fn program(&mut self, program: &Program) {
+        for item in &program.items {
+            match item {
+                Item::Comment(text) => self.comment(0, text),
+                Item::TrailingComment(text) => self.trailing(0, text),
+                Item::BlankLine => self.blank(),
+                Item::Function(function) => self.function(function),
+            }
+        }
+    }
+
+    // A dialect pragma names the dialect being written rather than the one read
+    fn comment(&mut self, depth: usize, text: &str) {
+        match Dialect::from_pragma(text) {
+            Some(_) => self.line(depth, &format!("//! dialect: {}", self.dialect.name())),
+            None => self.line(depth, text),
+        }
+        self.commented = text.starts_with("//");
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.function = function.name.clone();
+
+        for doc in &function.docs {
+            self.line(0, &doc_line(doc));
+        }
+
+        let keyword = self.dialect.function_keyword().as_str();
+        let header = match (self.dialect, function.params.is_empty()) {
+            (Dialect::Idi, _) => format!("{} {}, {}-", keyword, function.name, function.params.iter().map(|p| format!("{} ", p)).collect::<String>()),
+            (Dialect::Etu, true) => format!("{} {} -", keyword, function.name),
+            (Dialect::Etu, false) => format!("{} {}: {} -", keyword, function.name, function.params.join(", ")),
+        };
+        self.block(0, &header, &function.body);
+    }
+
+    /* A header and the block it opens. Comments trailing the header come first in the body,
+       whether they followed the header itself or the '|' after it, and stay on its line */
+    fn block(&mut self, depth: usize, header: &str, body: &[Statement]) {
+        self.line(depth, header);
+        let trailing = body.iter().take_while(|statement| matches!(statement, Statement::TrailingComment(_))).count();
+        body[..trailing].iter().for_each(|statement| self.statement(depth, statement));
+        let body = &body[trailing..];
+
+        match self.dialect {
+            Dialect::Idi => {
+                self.line(depth, "|");
+                body.iter().for_each(|statement| self.statement(depth + 1, statement));
+                self.line(depth, "~");
+            },
+            Dialect::Etu => {
+                if body.iter().all(|statement| matches!(statement, Statement::Comment(_) | Statement::TrailingComment(_) | Statement::BlankLine)) {
+                    self.unsupported("an empty body");
+                }
+                body.iter().for_each(|statement| self.statement(depth + 1, statement));
+            },
+        }
+    }
+
+    fn statement(&mut self, depth: usize, statement: &Statement) {
+        let terminator = match self.dialect {
+            Dialect::Idi => ".",
+            Dialect::Etu => "",
+        };
+
+        let text = match statement {
+            Statement::Declare(name) => format!("dec {}", name),
+            Statement::Return(value) => format!("^ {}", self.expr(value)),
+            Statement::Expr(value) => self.expr(value),
+            Statement::Comment(text) => return self.comment(depth, text),
+            Statement::TrailingComment(text) => return self.trailing(depth, text),
+            Statement::BlankLine => return self.blank(),
+            Statement::When(branches) => return self.when(depth, branches),
+        };
+        self.line(depth, &(text + terminator));
+    }
+
+    fn when(&mut self, depth: usize, branches: &[Branch]) {
+        for (i, branch) in branches.iter().enumerate() {
+            branch.leading.iter().for_each(|statement| self.statement(depth, statement));
+            let keyword = if i == 0 { "when" } else { "or when" };
+            let header = match (&branch.condition, self.dialect) {
+                (Some(condition), Dialect::Idi) => format!("{}, {} -", keyword, self.expr(condition)),
+                (Some(condition), Dialect::Etu) => format!("{}: {} -", keyword, self.expr(condition)),
+                (None, _) => "or else".to_string(),
+            };
+            self.block(depth, &header, &branch.body);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> String {
+        match expr {
+            Expr::Identifier(name) => name.clone(),
+            Expr::Literal(kind) => literal(kind),
+
+            // a call sending its result on lets '-->' close its arguments
+            Expr::Binary { op, lhs, rhs } if op == "->" && matches!(**lhs, Expr::Call { .. }) => {
+                format!("{} --> {}", self.open_call(lhs), self.expr(rhs))
+            },
+            Expr::Binary { op, lhs, rhs } => format!("{} {} {}", self.expr(lhs), op, self.expr(rhs)),
+            Expr::Postfix { op, operand } => format!("{}{}", self.expr(operand), op),
+
+            Expr::Call { .. } => format!("{} -", self.open_call(expr)),
+            Expr::Group(inner) if self.dialect == Dialect::Etu && matches!(**inner, Expr::Call { .. }) => format!("({})", self.open_call(inner)),
+            Expr::Group(inner) => format!("({})", self.expr(inner)),
+
+            Expr::List(values) => {
+                let values = values.iter().map(|value| self.expr(value)).collect::<Vec<_>>();
+                match self.dialect {
+                    Dialect::Idi => {
+                        self.unsupported("a ',' separated list");
+                        values.join(" ")
+                    },
+                    Dialect::Etu => values.join(", "),
+                }
+            },
+        }
+    }
+
+    // A call up to, but not including, the '-' closing its arguments
+    fn open_call(&mut self, call: &Expr) -> String {
+        let (name, args) = match call {
+            Expr::Call { name, args } => (name, args),
+            other => return self.expr(other),
+        };
+
+        // arguments other than single values are bracketed so they stay in one piece
+        let args = args.iter().map(|arg| match arg {
+            Expr::Identifier(_) | Expr::Literal(_) | Expr::Group(_) | Expr::Postfix { .. } => self.expr(arg),
+            Expr::Binary { .. } if self.dialect == Dialect::Etu => self.expr(arg),
+            _ => format!("({})", self.expr(arg)),
+        }).collect::<Vec<_>>();
+
+        match (self.dialect, args.is_empty()) {
+            (Dialect::Idi, true) => format!("{},", name),
+            (Dialect::Idi, false) => format!("{}, {}", name, args.join(" ")),
+            (Dialect::Etu, true) => format!("{}:", name),
+            (Dialect::Etu, false) => format!("{}: {}", name, args.join(", ")),
+        }
+    }
+}
+
+fn escape(text: &str, quote: char) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\n' => escaped += "\\n",
+            '\t' => escaped += "\\t",
+            '\0' => escaped += "\\0",
+            '\\' => escaped += "\\\\",
+            c if c == quote => { escaped.push('\\'); escaped.push(c); },
+            c if c.is_control() => escaped += &format!("\\u{{{:x}}}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+    format!("{}{}{}", quote, escaped, quote)
+}
+
+fn literal(kind: &TokenType) -> String {
+    match kind {
+        TokenType::Numeric{ raw, kind: NumericKind::Bool, .. } => (raw != "0").to_string(),
+        TokenType::Numeric{ raw, base, suffix, .. } => {
+            let prefix = match base {
+                NumericBase::Decimal | NumericBase::Any => String::new(),
+                base => format!("{}b", base.radix()),
+            };
+            let suffix = suffix.as_ref().map(ToString::to_string).unwrap_or_default();
+            format!("{}{}{}", prefix, raw, suffix)
+        },
+        TokenType::String(text) => escape(text, '"'),
+        TokenType::Character(c) => escape(&c.to_string(), '\''),
+        TokenType::Keywords(keyword) => keyword.as_str().to_string(),
+        _ => String::new(),
+    }
+}
@@ -2,12 +2,15 @@ extern crate idiom_core;
 extern crate clap;
 
 use idiom_core::lexer::*;
+use idiom_core::parser::*;
 
-use clap::{App, SubCommand};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
 
-fn main() -> std::io::Result<()> {
-    println!("Hello, Lexer!\n");
+use clap::{App, Arg, SubCommand};
 
+fn main() -> std::io::Result<()> {
     let application = App::new("Idiom")
         .version("0.1a")
         .author(" ")
@@ -17,33 +20,86 @@ fn main() -> std::io::Result<()> {
         .subcommand(SubCommand::with_name("debug").args_from_usage(
             "
             --show=[TOKENS]...   'Show specific steps in the compiling process (tokens, ast, ..)'
-            <INPUT>     'File to load'
+            <INPUT>     'File to load, or - for standard input'
 
             "
         ))
+        .subcommand(SubCommand::with_name("translate")
+            .about("Rewrites a source file in the other dialect, reporting anything it has no way to say")
+            .arg(Arg::from_usage("--to=<DIALECT>   'Dialect to write'").possible_values(&["idi", "etu"]))
+            .arg(Arg::from_usage("<INPUT>   'File to translate'"))
+        )
         .get_matches();
 
-    match application.subcommand() {
-        ("debug", Some(matching)) => { 
-            let filename = matching.value_of("INPUT").unwrap();
-            let text = std::fs::read_to_string(filename)?;
-            let lexer = Lexer::new(&text);
-            let shows = matching.values_of("show").unwrap_or_default().collect::<Vec<&str>>();
-            if shows.contains(&"tokens") {
-                let mut lexer = lexer.clone();
- 
-                loop {
-                    match lexer.next_token() {
-                        Ok(TokenType::EOF) => { println!("Breaking.. EOF.. "); break; },
-                        Ok(tok) => println!("{0:?}", tok),
-                        Err(err) => println!("{0:?}", err),
-                    }
+    if let ("translate", Some(matching)) = application.subcommand() {
+        let filename = matching.value_of("INPUT").unwrap();
+        let text = std::fs::read_to_string(filename)?;
+        let from = Dialect::detect(Some(Path::new(filename)), &text);
+        let to = matching.value_of("to").and_then(Dialect::from_name).unwrap_or_default();
+
+        match translate(&text, from, to) {
+            Ok(translation) => {
+                print!("{}", translation.text);
+                for note in translation.unsupported {
+                    eprintln!("{}: {}", filename, note);
                 }
+            },
+            Err(err) => {
+                eprintln!("{}:{}:{}: {}", filename, err.span().start.line, err.span().start.col, err);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    if let ("debug", Some(matching)) = application.subcommand() {
+        println!("Hello, Lexer!\n");
+
+        let filename = matching.value_of("INPUT").unwrap();
+        let shows = matching.values_of("show").unwrap_or_default().collect::<Vec<&str>>();
+
+        // '-' reads standard input, so piped sources are lexed as they arrive
+        let mut input: Box<dyn BufRead> = match filename {
+            "-" => Box::new(io::stdin().lock()),
+            _ => Box::new(BufReader::new(File::open(filename)?)),
+        };
+        let dialect = Dialect::detect(Some(Path::new(filename)), &String::from_utf8_lossy(input.fill_buf()?));
+
+        // the syntax tree needs the whole text, tokens alone are lexed straight off the input
+        let mut text = String::new();
+        if shows.contains(&"ast") {
+            input.read_to_string(&mut text)?;
+        }
+
+        if shows.contains(&"tokens") {
+            match shows.contains(&"ast") {
+                true => show_tokens(filename, ReadLexer::with_config(text.as_bytes(), dialect.config())),
+                false => show_tokens(filename, ReadLexer::with_config(&mut input, dialect.config())),
+            }
+        }
+        if shows.contains(&"ast") {
+            match parse(&text, dialect) {
+                Ok(program) => println!("{:#?}", program),
+                Err(err) => println!("{}:{}:{}: {}", filename, err.span().start.line, err.span().start.col, err),
             }
         }
-        _ => {}
     }
 
     Ok(())
 
 }
+
+fn show_tokens<R: Read>(filename: &str, mut lexer: ReadLexer<R>) {
+    lexer.set_recovery(true);
+
+    for token in lexer.by_ref() {
+        match token {
+            Ok(Token { kind: TokenType::EOF, .. }) => println!("Breaking.. EOF.. "),
+            Ok(tok) => println!("{0:?}", tok),
+            Err(err) => println!("{0:?}", err),
+        }
+    }
+
+    for err in lexer.diagnostics() {
+        println!("{}:{}:{}: {}", filename, err.span().start.line, err.span().start.col, err);
+    }
+}
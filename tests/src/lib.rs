@@ -7,6 +7,31 @@ pub use idiom_core::*;
 #[cfg(test)]
 mod tests {
 
+    fn kinds(src: &str) -> Vec<crate::TokenType<'_>> {
+        let mut lexer = crate::Lexer::new(src);
+        let mut kinds = Vec::new();
+
+        loop {
+            match lexer.next_token().unwrap().kind {
+                crate::TokenType::EOF => break kinds,
+                kind => kinds.push(kind),
+            }
+        }
+    }
+
+    // Kinds up to EOF with recovery on, leaving the diagnostics on the lexer
+    fn recovered_kinds<'a>(lexer: &mut crate::Lexer<'a>) -> Vec<crate::TokenType<'a>> {
+        lexer.set_recovery(true);
+        let mut kinds = Vec::new();
+
+        loop {
+            match lexer.next_token().unwrap().kind {
+                crate::TokenType::EOF => break kinds,
+                kind => kinds.push(kind),
+            }
+        }
+    }
+
     #[test]
     fn type_eof() {
         assert_eq!(tokenize!(EOF), crate::TokenType::EOF)
@@ -22,61 +47,70 @@ mod tests {
 
     #[test]
     fn type_numerics() {
-        assert_eq!(tokenize!(Num ("634".to_string()) Den WholeNo), 
+        assert_eq!(tokenize!(Num ("634".to_string()) Dec WholeNo), 
                    crate::TokenType::Numeric{ 
-                       raw: "634".to_string(), 
+                       raw: "634".into(), 
                        base: crate::NumericBase::Decimal, 
-                       kind: crate::NumericKind::Whole 
+                       kind: crate::NumericKind::Whole,
+                       suffix: None
                    });
 
         assert_eq!(tokenize!(Num ("1011010".to_string()) Bin WholeNo), 
                    crate::TokenType::Numeric{ 
-                       raw: "1011010".to_string(), 
+                       raw: "1011010".into(), 
                        base: crate::NumericBase::Binary, 
-                       kind: crate::NumericKind::Whole 
+                       kind: crate::NumericKind::Whole,
+                       suffix: None
                    });
 
         assert_eq!(tokenize!(Num ("9F3204AC".to_string()) Hex WholeNo), 
                    crate::TokenType::Numeric{ 
-                       raw: "9F3204AC".to_string(), 
+                       raw: "9F3204AC".into(), 
                        base: crate::NumericBase::Hexadecimal, 
-                       kind: crate::NumericKind::Whole 
+                       kind: crate::NumericKind::Whole,
+                       suffix: None
                    });
         assert_eq!(tokenize!(Num ("/.^7HU2,".to_string()) B64 WholeNo), 
                    crate::TokenType::Numeric{ 
-                       raw: "/.^7HU2,".to_string(), 
+                       raw: "/.^7HU2,".into(), 
                        base: crate::NumericBase::Base64, 
-                       kind: crate::NumericKind::Whole 
+                       kind: crate::NumericKind::Whole,
+                       suffix: None
                    });
-        assert_eq!(tokenize!(Num ("1e+194".to_string()) Den Exponent), 
+        assert_eq!(tokenize!(Num ("1e+194".to_string()) Dec Exponent), 
                    crate::TokenType::Numeric{ 
-                       raw: "1e+194".to_string(), 
+                       raw: "1e+194".into(), 
                        base: crate::NumericBase::Decimal, 
-                       kind: crate::NumericKind::Exponential 
+                       kind: crate::NumericKind::Exponential,
+                       suffix: None
                    });
-        assert_eq!(tokenize!(Num ("0.11235".to_string()) Den Fraction), 
+        assert_eq!(tokenize!(Num ("0.11235".to_string()) Dec Fraction), 
                    crate::TokenType::Numeric{ 
-                       raw: "0.11235".to_string(), 
+                       raw: "0.11235".into(), 
                        base: crate::NumericBase::Decimal, 
-                       kind: crate::NumericKind::Fractional
+                       kind: crate::NumericKind::Fractional,
+                       suffix: None
                    });
-        assert_eq!(tokenize!(Num ("3.14".to_string()) Den Fraction),                   
+        assert_eq!(tokenize!(Num ("3.14".to_string()) Dec Fraction),                   
                    crate::TokenType::Numeric{ 
-                       raw: "3.14".to_string(), 
+                       raw: "3.14".into(), 
                        base: crate::NumericBase::Decimal, 
-                       kind: crate::NumericKind::Fractional
+                       kind: crate::NumericKind::Fractional,
+                       suffix: None
                    });
         assert_eq!(tokenize!(Num ("True".to_string()) Bin Boolean), 
                    crate::TokenType::Numeric{ 
-                       raw: "True".to_string(), 
+                       raw: "True".into(), 
                        base: crate::NumericBase::Binary, 
-                       kind: crate::NumericKind::Bool
+                       kind: crate::NumericKind::Bool,
+                       suffix: None
                    });
         assert_eq!(tokenize!(Num ("0".to_string()) Bin Boolean),
                    crate::TokenType::Numeric{ 
-                       raw: "0".to_string(), 
+                       raw: "0".into(), 
                        base: crate::NumericBase::Binary, 
-                       kind: crate::NumericKind::Bool
+                       kind: crate::NumericKind::Bool,
+                       suffix: None
                    });
     }
 
@@ -84,4 +118,1293 @@ mod tests {
     fn type_characters() {
         assert_eq!(tokenize!(Char 'c'), crate::TokenType::Character('c'))
     }
+
+    #[test]
+    fn token_spans() {
+        let mut lexer = crate::Lexer::new("12\n  \"é\" 3");
+
+        let tok = lexer.next_token().unwrap();
+        assert_eq!(tok.span.start, crate::Position { offset: 0, line: 1, col: 1 });
+        assert_eq!(tok.span.end, crate::Position { offset: 2, line: 1, col: 3 });
+
+        let tok = lexer.next_token().unwrap();
+        assert_eq!(tok.kind, crate::TokenType::String("é".into()));
+        assert_eq!(tok.span.start, crate::Position { offset: 5, line: 2, col: 3 });
+        assert_eq!(tok.span.end, crate::Position { offset: 9, line: 2, col: 6 });
+
+        let tok = lexer.next_token().unwrap();
+        assert_eq!(tok.span.start, crate::Position { offset: 10, line: 2, col: 7 });
+
+        let tok = lexer.next_token().unwrap();
+        assert_eq!(tok.kind, tokenize!(EOF));
+        assert_eq!(tok.span.start, tok.span.end);
+    }
+
+    #[test]
+    fn error_spans() {
+        let mut lexer = crate::Lexer::new("203 16bG");
+        lexer.next_token().unwrap();
+
+        let err = lexer.next_token().unwrap_err();
+        assert!(matches!(err, crate::LexerError::InvalidHexadecimalValue { .. }));
+        assert_eq!(err.span().start.offset, 4);
+        assert_eq!(err.span().end.offset, 8);
+    }
+
+    #[test]
+    fn type_keywords() {
+        assert_eq!(crate::KeywordsKind::lookup("when"), Some(crate::KeywordsKind::When));
+        assert_eq!(crate::KeywordsKind::lookup("When"), None);
+        assert_eq!(crate::KeywordsKind::Snd.as_str(), "snd");
+        assert!(crate::is_keyword("entry"));
+        assert!(!crate::is_keyword("userIn"));
+        assert_eq!(crate::KEYWORDS.len(), 23);
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(kinds("go printer dec value_2 userIn _tmp élan"), vec![
+            tokenize!(Keyword Go),
+            tokenize!(Ident "printer"),
+            tokenize!(Keyword Dec),
+            tokenize!(Ident "value_2"),
+            tokenize!(Ident "userIn"),
+            tokenize!(Ident "_tmp"),
+            tokenize!(Ident "élan"),
+        ]);
+    }
+
+    #[test]
+    fn operators() {
+        let op = |raw: &'static str| crate::TokenType::Operators(raw.into());
+
+        assert_eq!(kinds("log <- words"), vec![tokenize!(Ident "log"), op("<-"), tokenize!(Ident "words")]);
+        assert_eq!(kinds("userIn -> arg1"), vec![tokenize!(Ident "userIn"), op("->"), tokenize!(Ident "arg1")]);
+        assert_eq!(kinds("^ (a + b * c / d)"), vec![
+            op("^"),
+            tokenize!(Delimit '(' (Open 0)),
+            tokenize!(Ident "a"), op("+"), tokenize!(Ident "b"), op("*"), tokenize!(Ident "c"), op("/"), tokenize!(Ident "d"),
+            tokenize!(Delimit ')' (Close 0)),
+        ]);
+        assert_eq!(kinds("takeInput?"), vec![tokenize!(Ident "takeInput"), op("?")]);
+    }
+
+    #[test]
+    fn operators_against_delimiters() {
+        let op = |raw: &'static str| crate::TokenType::Operators(raw.into());
+
+        // '<' after an operand compares, '>' only closes an open vector
+        assert_eq!(kinds("when, arg1 < arg2 -"), vec![
+            tokenize!(Keyword When),
+            tokenize!(Delimit ',' (Open 0)),
+            tokenize!(Ident "arg1"), op("<"), tokenize!(Ident "arg2"),
+            tokenize!(Delimit '-' (Close 0)),
+        ]);
+        assert_eq!(kinds("arg1 > arg2"), vec![tokenize!(Ident "arg1"), op(">"), tokenize!(Ident "arg2")]);
+        assert_eq!(kinds("<1 2>"), vec![
+            tokenize!(Delimit '<' (Open 0)),
+            tokenize!(Num ("1".to_string()) Dec WholeNo),
+            tokenize!(Num ("2".to_string()) Dec WholeNo),
+            tokenize!(Delimit '>' (Close 0)),
+        ]);
+
+        // '-->' closes the parameter list it ends, so the list is not left open
+        let mut lexer = crate::Lexer::new("add, arg1 arg2 --> value ~");
+        for _ in 0..6 {
+            lexer.next_token().unwrap();
+        }
+        assert!(matches!(lexer.next_token(), Err(crate::LexerError::MisMatchedDelimiters { symbol: '~', .. })));
+    }
+
+    #[test]
+    fn dash_disambiguation() {
+        let op = |raw: &'static str| crate::TokenType::Operators(raw.into());
+
+        assert_eq!(kinds("go subtract, arg1 arg2 -\n|   ^ (arg1 - arg2)\n~"), vec![
+            tokenize!(Keyword Go),
+            tokenize!(Ident "subtract"),
+            tokenize!(Delimit ',' (Open 0)),
+            tokenize!(Ident "arg1"), tokenize!(Ident "arg2"),
+            tokenize!(Delimit '-' (Close 0)),
+            tokenize!(Delimit '|' (Open 0)),
+            op("^"),
+            tokenize!(Delimit '(' (Open 0)),
+            tokenize!(Ident "arg1"), op("-"), tokenize!(Ident "arg2"),
+            tokenize!(Delimit ')' (Close 0)),
+            tokenize!(Delimit '~' (Close 0)),
+        ]);
+
+        // inside a parameter list: subtraction between operands, closing otherwise
+        assert_eq!(kinds("when, arg1 - 1 < arg2 -"), vec![
+            tokenize!(Keyword When),
+            tokenize!(Delimit ',' (Open 0)),
+            tokenize!(Ident "arg1"), op("-"), tokenize!(Num ("1".to_string()) Dec WholeNo),
+            op("<"), tokenize!(Ident "arg2"),
+            tokenize!(Delimit '-' (Close 0)),
+        ]);
+        assert_eq!(kinds("go main, -"), vec![
+            tokenize!(Keyword Go),
+            tokenize!(Ident "main"),
+            tokenize!(Delimit ',' (Open 0)),
+            tokenize!(Delimit '-' (Close 0)),
+        ]);
+        assert_eq!(kinds("add, (a - b) c -"), vec![
+            tokenize!(Ident "add"),
+            tokenize!(Delimit ',' (Open 0)),
+            tokenize!(Delimit '(' (Open 0)),
+            tokenize!(Ident "a"), op("-"), tokenize!(Ident "b"),
+            tokenize!(Delimit ')' (Close 0)),
+            tokenize!(Ident "c"),
+            tokenize!(Delimit '-' (Close 0)),
+        ]);
+
+        // a parser can override the guess
+        let mut lexer = crate::Lexer::new("f, a - b");
+        for _ in 0..3 {
+            lexer.next_token().unwrap();
+        }
+        lexer.set_dash_mode(crate::DashMode::Delimiter);
+        assert_eq!(lexer.next_token().unwrap().kind, tokenize!(Delimit '-' (Close 0)));
+    }
+
+    #[test]
+    fn terminators() {
+        assert_eq!(kinds("dec value."), vec![tokenize!(Keyword Dec), tokenize!(Ident "value"), tokenize!(Terminator)]);
+        assert_eq!(kinds("^ 0."), vec![
+            crate::TokenType::Operators("^".into()),
+            tokenize!(Num ("0".to_string()) Dec WholeNo),
+            tokenize!(Terminator),
+        ]);
+        assert_eq!(kinds(".798 .e"), vec![
+            tokenize!(Num (".798".to_string()) Dec Fraction),
+            tokenize!(Terminator),
+            tokenize!(Ident "e"),
+        ]);
+        assert_eq!(kinds("3 .5"), vec![
+            tokenize!(Num ("3".to_string()) Dec WholeNo),
+            tokenize!(Num (".5".to_string()) Dec Fraction),
+        ]);
+
+        // a dot after a fraction ends the statement as it does after any other number
+        let f32 = crate::NumericSuffix { signedness: crate::Signedness::Float, width: 32 };
+        for (src, raw, suffix) in [(".2.", ".2", None), ("0.5.", "0.5", None), ("1.5e3.", "1.5e3", None), ("3.0f32.", "3.0", Some(f32))] {
+            let kind = if raw.contains('e') { crate::NumericKind::Exponential } else { crate::NumericKind::Fractional };
+            assert_eq!(kinds(src), vec![
+                crate::TokenType::Numeric { raw: raw.into(), base: crate::NumericBase::Decimal, kind, suffix },
+                tokenize!(Terminator),
+            ], "{}", src);
+        }
+
+        // but fractions may not run into more digits, nor can other bases have them, exponents need digits
+        for src in ["1.2.3", ".2.5", "16b16.4", "2b1.1"] {
+            let err = crate::Lexer::new(src).next_token().unwrap_err();
+            assert!(matches!(err, crate::LexerError::InvalidFractionalValue { .. }), "{}", src);
+            assert_eq!(err.span().end.offset, src.len());
+        }
+        match crate::Lexer::new(".1e\n").next_token() {
+            Err(crate::LexerError::UnknownNumericLiteral { raw, received, .. }) => assert_eq!((raw.as_str(), received), (".1e", '\n')),
+            other => panic!("expected an unknown numeric literal, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn function_sample() {
+        let kinds = kinds(include_str!("../idiom_files/function.idi"));
+
+        assert_eq!(kinds.iter().filter(|k| **k == tokenize!(Terminator)).count(), 11);
+        assert_eq!(kinds.iter().filter(|k| **k == tokenize!(Delimit '-' (Close 0))).count(), 10);
+        assert_eq!(kinds.iter().filter(|k| **k == crate::TokenType::Operators("-".into())).count(), 1);
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(kinds(include_str!("../idiom_files/strings.idi")), vec![
+            crate::TokenType::String("this is a string".into()),
+            crate::TokenType::String("is this delimited\n \"something \" ".into()),
+        ]);
+        assert_eq!(kinds(r#""tab\tnul\0slash\\ \u{48}\u{1F600}""#), vec![
+            crate::TokenType::String("tab\tnul\0slash\\ H\u{1F600}".into()),
+        ]);
+
+        let mut lexer = crate::Lexer::new(r#""ab\q" "\u{110000}" "\u{}" 7"#);
+        for sequence in ["\\q", "\\u{110000}", "\\u{}"] {
+            match lexer.next_token() {
+                Err(crate::LexerError::InvalidEscapeSequence { sequence: found, span }) => {
+                    assert_eq!(found, sequence);
+                    assert_eq!(lexer.slice(span), sequence);
+                },
+                other => panic!("expected an invalid escape, got {:?}", other),
+            }
+        }
+        assert_eq!(lexer.next_token().unwrap().kind, tokenize!(Num ("7".to_string()) Dec WholeNo));
+    }
+
+    #[test]
+    fn source_slices() {
+        let src = "log <- \"a\\tb\".";
+        let mut lexer = crate::Lexer::new(src);
+        let mut raw = Vec::new();
+
+        loop {
+            let tok = lexer.next_token().unwrap();
+            if tok.kind == tokenize!(EOF) {
+                break;
+            }
+            raw.push(lexer.slice(tok.span));
+        }
+        assert_eq!(raw, vec!["log", "<-", "\"a\\tb\"", "."]);
+    }
+
+    #[test]
+    fn character_literals() {
+        assert_eq!(kinds(r"'c' '\n' '\'' '\u{e9}' 'é'"), vec![
+            tokenize!(Char 'c'),
+            tokenize!(Char '\n'),
+            tokenize!(Char '\''),
+            tokenize!(Char 'é'),
+            tokenize!(Char 'é'),
+        ]);
+
+        let mut lexer = crate::Lexer::new("'' 'ab' '\\x' 'z\n1");
+        assert!(matches!(lexer.next_token(), Err(crate::LexerError::EmptyCharacterLiteral { .. })));
+        assert!(matches!(lexer.next_token(), Err(crate::LexerError::MultiCharacterLiteral { raw, .. }) if raw == "'ab'"));
+        assert!(matches!(lexer.next_token(), Err(crate::LexerError::InvalidEscapeSequence { .. })));
+        match lexer.next_token() {
+            Err(crate::LexerError::UnterminatedCharacterLiteral { raw, span }) => {
+                assert_eq!(raw, "'z");
+                assert_eq!(span.start.col, 14);
+            },
+            other => panic!("expected an unterminated literal, got {:?}", other),
+        }
+        assert_eq!(lexer.next_token().unwrap().kind, tokenize!(Num ("1".to_string()) Dec WholeNo));
+    }
+
+    #[test]
+    fn boolean_literals() {
+        let mut lexer = crate::Lexer::new("true false 2b1 truth");
+
+        let tok = lexer.next_token().unwrap();
+        assert_eq!(tok.kind, tokenize!(Num ("1".to_string()) Bin Boolean));
+        assert_eq!(tok.kind.as_bool(), Some(true));
+        assert_eq!(lexer.slice(tok.span), "true");
+
+        let tok = lexer.next_token().unwrap();
+        assert_eq!(tok.kind, tokenize!(Num ("0".to_string()) Bin Boolean));
+        assert_eq!(tok.kind.as_bool(), Some(false));
+
+        // explicit binary digits stay whole numbers
+        let tok = lexer.next_token().unwrap();
+        assert_eq!(tok.kind, tokenize!(Num ("1".to_string()) Bin WholeNo));
+        assert_eq!(tok.kind.as_bool(), None);
+
+        assert_eq!(lexer.next_token().unwrap().kind, tokenize!(Ident "truth"));
+    }
+
+    #[test]
+    fn base64_literals() {
+        assert_eq!(kinds("(64bf02/523+2393f0jaf)."), vec![
+            tokenize!(Delimit '(' (Open 0)),
+            tokenize!(Num ("f02/523+2393f0jaf".to_string()) B64 WholeNo),
+            tokenize!(Delimit ')' (Close 0)),
+            tokenize!(Terminator),
+        ]);
+
+        let mut lexer = crate::Lexer::new("64bf02/(523&2393f0jaf )");
+        match lexer.next_token() {
+            Err(crate::LexerError::InvalidBase64Digit { raw, invalid, .. }) => {
+                assert_eq!(raw, "f02/(523&2393f0jaf");
+                assert_eq!(invalid, "(");
+            },
+            other => panic!("expected an invalid Base64 digit, got {:?}", other),
+        }
+        // the '(' belonged to the rejected literal, so nothing is open for ')' to close
+        assert!(matches!(lexer.next_token(), Err(crate::LexerError::MisMatchedDelimiters { symbol: ')', .. })));
+
+        assert_eq!(crate::decode_base64("BA"), Some(64u32.into()));
+        assert_eq!(crate::decode_base64("/"), Some(63u32.into()));
+        assert_eq!(crate::decode_base64("A&"), None);
+        assert_eq!(crate::base64_digit('+'), Some(62));
+        assert_eq!(crate::BASE64_DIGITS.chars().nth(52), Some('0'));
+    }
+
+    fn evaluate(src: &str) -> crate::Evaluated {
+        crate::evaluate(&crate::Lexer::new(src).next_token().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn numeric_values() {
+        let int = |n: u64| crate::NumericValue::Integer(n.into());
+
+        assert_eq!(evaluate("16b10FA").value, int(0x10FA));
+        assert_eq!(evaluate("2b1010").value, int(10));
+        assert_eq!(evaluate("8b123751").value, int(0o123751));
+        assert_eq!(evaluate("64bBA").value, int(64));
+        assert_eq!(evaluate("true").value, crate::NumericValue::Bool(true));
+        assert_eq!(evaluate("99999999999999999999999999").value.to_literal(&crate::NumericBase::Decimal),
+                   Some("99999999999999999999999999".to_string()));
+
+        let fraction = evaluate("0.11235");
+        assert!(fraction.warnings.is_empty());
+        match &fraction.value {
+            crate::NumericValue::Rational(r) => assert_eq!((r.numer().to_string(), r.denom().to_string()), ("2247".to_string(), "20000".to_string())),
+            other => panic!("expected a rational, got {:?}", other),
+        }
+
+        assert_eq!(evaluate("1e3").value, crate::NumericValue::Float(1000.0));
+        assert!(evaluate("1.5e3").warnings.is_empty());
+        assert!(matches!(evaluate("1e+49").warnings.as_slice(), [crate::NumericWarning::PrecisionLoss { .. }]));
+        assert!(matches!(evaluate("1e400").warnings.as_slice(), [crate::NumericWarning::Overflow { .. }]));
+        assert!(matches!(evaluate("1e-99999").warnings.as_slice(), [crate::NumericWarning::PrecisionLoss { .. }]));
+
+        let not_numeric = crate::Lexer::new("value").next_token().unwrap();
+        assert!(matches!(crate::evaluate(&not_numeric), Err(crate::LexerError::ImproperUsage { .. })));
+    }
+
+    #[test]
+    fn numeric_round_trip() {
+        for (src, base) in [
+            ("16b10FA", crate::NumericBase::Hexadecimal),
+            ("2b1010", crate::NumericBase::Binary),
+            ("8b17", crate::NumericBase::Octal),
+            ("64bf02/523+2393f0jaf", crate::NumericBase::Base64),
+            ("203", crate::NumericBase::Decimal),
+            ("0.11235", crate::NumericBase::Decimal),
+            ("3.0", crate::NumericBase::Decimal),
+            ("0.5", crate::NumericBase::Decimal),
+            ("1e49", crate::NumericBase::Decimal),
+            ("false", crate::NumericBase::Binary),
+        ] {
+            assert_eq!(evaluate(src).value.to_literal(&base).as_deref(), Some(src));
+        }
+
+        assert_eq!(evaluate(".798").value.to_literal(&crate::NumericBase::Decimal).as_deref(), Some("0.798"));
+        assert_eq!(evaluate("16b10FA").value.to_literal(&crate::NumericBase::Decimal).as_deref(), Some("4346"));
+    }
+
+    #[test]
+    fn radix_literals() {
+        assert_eq!(kinds("3b2101 32bVV 36bZZ 16bBEEF"), vec![
+            tokenize!(Num ("2101".to_string()) 3 WholeNo),
+            tokenize!(Num ("VV".to_string()) 32 WholeNo),
+            tokenize!(Num ("ZZ".to_string()) 36 WholeNo),
+            tokenize!(Num ("BEEF".to_string()) Hex WholeNo),
+        ]);
+        assert_eq!(evaluate("3b2101").value, crate::NumericValue::Integer(64u32.into()));
+        assert_eq!(evaluate("36bZZ").value.to_literal(&crate::NumericBase::Radix(36)).as_deref(), Some("36bZZ"));
+        assert_eq!(crate::NumericBase::from_radix(37), None);
+
+        for src in ["1b0", "37b1", "0b1", "48b1239419", "1_6b1"] {
+            assert!(matches!(crate::Lexer::new(src).next_token(), Err(crate::LexerError::InvalidNumericBase { .. })), "{}", src);
+        }
+        assert!(matches!(crate::Lexer::new("3b123").next_token(),
+            Err(crate::LexerError::InvalidNumericLiteral { base: crate::NumericBase::Radix(3), .. })));
+    }
+
+    #[test]
+    fn digit_separators() {
+        assert_eq!(kinds("2b1010_1100 1_000_000 3.141_592"), vec![
+            tokenize!(Num ("1010_1100".to_string()) Bin WholeNo),
+            tokenize!(Num ("1_000_000".to_string()) Dec WholeNo),
+            tokenize!(Num ("3.141_592".to_string()) Dec Fraction),
+        ]);
+        assert_eq!(evaluate("2b1010_1100").value, crate::NumericValue::Integer(0b1010_1100u32.into()));
+        assert_eq!(evaluate("1_000_000").value, crate::NumericValue::Integer(1_000_000u32.into()));
+        assert_eq!(evaluate("1_0e1_0").value, crate::NumericValue::Float(1e11));
+
+        for src in ["1_", "1__0", "2b_1", "16bFF_", "1_e5"] {
+            assert!(matches!(crate::Lexer::new(src).next_token(), Err(crate::LexerError::MisplacedDigitSeparator { .. })), "{}", src);
+        }
+    }
+
+    #[test]
+    fn numeric_suffixes() {
+        assert_eq!(kinds("42u8 16bFFi32 3.14f32 1e5f64 2b1010_1100u8 16bFFf32"), vec![
+            tokenize!(Num ("42".to_string()) Dec WholeNo u8),
+            tokenize!(Num ("FF".to_string()) Hex WholeNo i32),
+            tokenize!(Num ("3.14".to_string()) Dec Fraction f32),
+            tokenize!(Num ("1e5".to_string()) Dec Exponent f64),
+            tokenize!(Num ("1010_1100".to_string()) Bin WholeNo u8),
+            // 'f' is a hexadecimal digit, so there is no suffix to split off
+            tokenize!(Num ("FFf32".to_string()) Hex WholeNo),
+        ]);
+        assert_eq!(call_numeric_suffix!(i128).to_string(), "i128");
+
+        for src in ["2b1_0000_0000u8", "128i8", "256u8", "3.14u8", "1e39f32", "1e5i64"] {
+            match crate::Lexer::new(src).next_token() {
+                Err(crate::LexerError::UnrepresentableLiteral { raw, .. }) => assert_eq!(raw, src),
+                other => panic!("expected {} to be unrepresentable, got {:?}", src, other),
+            }
+        }
+        for src in ["255u8", "127i8", "340282366920938463463374607431768211455u128", "1e38f32"] {
+            assert!(crate::Lexer::new(src).next_token().is_ok(), "{}", src);
+        }
+    }
+
+    #[test]
+    fn error_recovery() {
+        let mut lexer = crate::Lexer::new(include_str!("../idiom_files/numbers.idi"));
+        lexer.set_recovery(true);
+
+        let mut errors = Vec::new();
+        loop {
+            let tok = lexer.next_token().unwrap();
+            match tok.kind {
+                crate::TokenType::EOF => break,
+                crate::TokenType::Error(raw) => errors.push(raw),
+                _ => {},
+            }
+        }
+
+        assert_eq!(errors, vec!["16b10FG23", "16b16.4", "48b1239419", "16bG", ".1e", "2b3", "16bG", "64bf02/(523&2393f0jaf", "8b99"]);
+
+        let diagnostics = lexer.take_diagnostics();
+        assert_eq!(diagnostics.len(), errors.len());
+        assert!(matches!(diagnostics[1], crate::LexerError::InvalidFractionalValue { .. }));
+        assert!(matches!(diagnostics[2], crate::LexerError::InvalidNumericBase { .. }));
+        assert_eq!(diagnostics.iter().map(|err| err.span().start.line).collect::<Vec<_>>(), vec![3, 4, 5, 7, 14, 18, 20, 21, 23]);
+        assert!(lexer.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn error_recovery_keeps_state() {
+        let mut lexer = crate::Lexer::new("go f, $ a 16bZ -| ^ a. ~");
+        let kinds = recovered_kinds(&mut lexer);
+
+        assert_eq!(kinds[3], crate::TokenType::Error("$".into()));
+        assert_eq!(kinds[5], crate::TokenType::Error("16bZ".into()));
+        assert_eq!(kinds[6], tokenize!(Delimit '-' (Close 0)));
+        assert_eq!(kinds.last(), Some(&tokenize!(Delimit '~' (Close 0))));
+        assert_eq!(lexer.diagnostics().len(), 2);
+    }
+
+    #[test]
+    fn unclosed_delimiters() {
+        let mut lexer = crate::Lexer::new("go f, a -\n| log <- (a + [b\n");
+        for _ in 0..13 {
+            lexer.next_token().unwrap();
+        }
+        assert_eq!(lexer.unclosed().iter().map(|open| open.raw).collect::<String>(), "|([");
+
+        for (symbol, requires, line, col) in [('[', ']', 2, 15), ('(', ')', 2, 10), ('|', '~', 2, 1)] {
+            match lexer.next_token() {
+                Err(crate::LexerError::UnclosedDelimiter { symbol: found, requires: closer, span }) => {
+                    assert_eq!((found, closer), (symbol, requires));
+                    assert_eq!((span.start.line, span.start.col), (line, col));
+                    assert_eq!(lexer.slice(span), symbol.to_string());
+                },
+                other => panic!("expected {:?} to be unclosed, got {:?}", symbol, other),
+            }
+        }
+        assert_eq!(lexer.next_token().unwrap().kind, tokenize!(EOF));
+    }
+
+    #[test]
+    fn crossed_delimiters() {
+        let mut lexer = crate::Lexer::new("( [ ) ]");
+        let kinds = recovered_kinds(&mut lexer);
+
+        assert_eq!(kinds, vec![
+            tokenize!(Delimit '(' (Open 0)),
+            tokenize!(Delimit '[' (Open 0)),
+            crate::TokenType::Error(")".into()),
+            tokenize!(Delimit ']' (Close 0)),
+        ]);
+
+        let diagnostics = lexer.take_diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        match &diagnostics[0] {
+            crate::LexerError::CrossedDelimiters { symbol, expected, open, span } => {
+                assert_eq!((*symbol, *expected), (')', ']'));
+                assert_eq!(open.start.offset, 2);
+                assert_eq!(span.start.offset, 4);
+            },
+            other => panic!("expected crossed delimiters, got {:?}", other),
+        }
+        assert!(matches!(diagnostics[1], crate::LexerError::UnclosedDelimiter { symbol: '(', .. }));
+    }
+
+    #[test]
+    fn configured_delimiters() {
+        use crate::DelimiterRole;
+
+        let config = crate::LexerConfig::default()
+            .with_pair(':', ';', DelimiterRole::Parameters)
+            .with_pair('|', '|', DelimiterRole::FunctionBody)
+            .without_role(DelimiterRole::Object);
+
+        let mut lexer = crate::Lexer::with_config("go f : a ; | a - b | { ", config);
+        let kinds = recovered_kinds(&mut lexer);
+
+        assert_eq!(kinds, vec![
+            tokenize!(Keyword Go),
+            tokenize!(Ident "f"),
+            tokenize!(Delimit ':' (Open 0)),
+            tokenize!(Ident "a"),
+            tokenize!(Delimit ';' (Close 0)),
+            tokenize!(Delimit '|' (Open 0)),
+            tokenize!(Ident "a"),
+            crate::TokenType::Operators("-".into()),
+            tokenize!(Ident "b"),
+            tokenize!(Delimit '|' (Close 0)),
+            crate::TokenType::Error("{".into()),
+        ]);
+
+        let diagnostics = lexer.take_diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(matches!(diagnostics[0], crate::LexerError::UnknownDelimiter { symbol: '{', .. }));
+    }
+
+    #[test]
+    fn lexer_iterator() {
+        let tokens = crate::Lexer::new("x <- 1.").collect::<Vec<_>>();
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[4].as_ref().unwrap().kind, tokenize!(EOF));
+
+        let tokens = crate::Lexer::new("( $ )").collect::<Vec<_>>();
+        assert_eq!(tokens.len(), 4);
+        assert!(matches!(tokens[1], Err(crate::LexerError::UnknownPokemon { .. })));
+        assert!(matches!(tokens[3], Ok(crate::Token { kind: crate::TokenType::EOF, .. })));
+    }
+
+    #[test]
+    fn token_stream() {
+        let mut stream = crate::TokenStream::new(crate::Lexer::new("go f , a - ."));
+
+        assert_eq!(stream.peek_nth(2).as_ref().unwrap().kind, tokenize!(Delimit ',' (Open 0)));
+        assert_eq!(stream.peek_nth(10).as_ref().unwrap().kind, tokenize!(EOF));
+
+        let start = stream.mark();
+        assert_eq!(stream.expect(tokenize!(Keyword Go)).unwrap().kind, tokenize!(Keyword Go));
+        assert_eq!(stream.expect(tokenize!(Ident "anything")).unwrap().kind, tokenize!(Ident "f"));
+        assert_eq!(stream.expect(tokenize!(Delimit ',' (Open 3))).unwrap().span.start.col, 6);
+
+        match stream.expect(tokenize!(Terminator)) {
+            Err(crate::LexerError::ImproperUsage { expected, found, span }) => {
+                assert_eq!(expected, tokenize!(Terminator));
+                assert_eq!(found.kind, tokenize!(Ident "a"));
+                assert_eq!(span.start.col, 8);
+            },
+            other => panic!("expected improper usage, got {:?}", other),
+        }
+        assert_eq!(stream.next_token().unwrap().kind, tokenize!(Ident "a"));
+
+        stream.rewind(start);
+        assert_eq!(stream.next_token().unwrap().kind, tokenize!(Keyword Go));
+
+        for _ in 0..8 {
+            stream.next_token().unwrap();
+        }
+        assert_eq!(stream.next_token().unwrap().kind, tokenize!(EOF));
+    }
+
+    #[test]
+    fn token_stream_over_exhausted_lexer() {
+        let mut lexer = crate::Lexer::new("dec x.\n");
+        lexer.by_ref().for_each(drop);
+
+        let mut stream = crate::TokenStream::new(lexer);
+        assert_eq!(stream.peek_nth(3).as_ref().unwrap().kind, tokenize!(EOF));
+        let eof = stream.next_token().unwrap();
+        assert_eq!((eof.kind, eof.span.start.line), (tokenize!(EOF), 2));
+        assert!(stream.expect(tokenize!(Terminator)).is_err());
+    }
+
+    fn rebuild(src: &str, indentation: bool) -> String {
+        let mut lexer = crate::Lexer::with_config(src, crate::LexerConfig::default().with_indentation(indentation));
+        lexer.set_lossless(true);
+
+        let tokens = lexer.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        tokens.iter().map(|token| lexer.lossless_text(token)).collect()
+    }
+
+    #[test]
+    fn lossless_round_trip() {
+        for src in [
+            include_str!("../idiom_files/function.idi"),
+            include_str!("../idiom_files/numbers.idi"),
+            include_str!("../idiom_files/strings.idi"),
+            include_str!("../idiom_files/symbols.idi"),
+            include_str!("../idiom_files/comments.idi"),
+            "  go f , a -\r\n\t| a $ 16bZ ~ \n\n  ( [ ",
+        ] {
+            assert_eq!(rebuild(src, false), src);
+            assert_eq!(rebuild(src, true), src);
+        }
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(kinds(include_str!("../idiom_files/comments.idi")), vec![
+            crate::TokenType::DocComment("Adds two numbers".into()),
+            crate::TokenType::DocComment(" together".into()),
+            tokenize!(Keyword Go),
+            tokenize!(Ident "add"),
+            tokenize!(Delimit ',' (Open 0)),
+            tokenize!(Ident "a"),
+            tokenize!(Ident "b"),
+            tokenize!(Delimit '-' (Close 0)),
+            tokenize!(Delimit '|' (Open 0)),
+            crate::TokenType::Operators("^".into()),
+            tokenize!(Delimit '(' (Open 0)),
+            tokenize!(Ident "a"),
+            crate::TokenType::Operators("+".into()),
+            tokenize!(Ident "b"),
+            tokenize!(Delimit ')' (Close 0)),
+            tokenize!(Terminator),
+            tokenize!(Delimit '~' (Close 0)),
+            tokenize!(Keyword Dec),
+            tokenize!(Ident "x"),
+            tokenize!(Terminator),
+            tokenize!(Ident "x"),
+            crate::TokenType::Operators("<-".into()),
+            tokenize!(Num ("1".to_string()) Dec WholeNo),
+            tokenize!(Terminator),
+        ]);
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        let mut lexer = crate::Lexer::new("x.\n  /* open /* nested */ \n");
+        lexer.next_token().unwrap();
+        lexer.next_token().unwrap();
+
+        match lexer.next_token() {
+            Err(crate::LexerError::UnterminatedBlockComment { span }) => {
+                assert_eq!((span.start.line, span.start.col), (2, 3));
+                assert_eq!(span.end.line, 3);
+            },
+            other => panic!("expected an unterminated block comment, got {:?}", other),
+        }
+
+        let mut lexer = crate::Lexer::new("x /* open");
+        lexer.set_lossless(true);
+        let tokens = lexer.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(tokens[1].kind, tokenize!(EOF));
+        assert_eq!(tokens[1].leading[0].kind, crate::TriviaKind::BlockComment);
+        assert!(matches!(lexer.diagnostics(), [crate::LexerError::UnterminatedBlockComment { .. }]));
+    }
+
+    fn indented(src: &str) -> Vec<crate::TokenType<'_>> {
+        let mut lexer = crate::Lexer::with_config(src, crate::LexerConfig::default().with_indentation(true));
+        lexer.by_ref().map(|token| token.unwrap().kind).collect()
+    }
+
+    #[test]
+    fn indentation() {
+        use crate::TokenType::{Indent, Dedent, Terminator};
+
+        assert_eq!(indented(include_str!("../../hello_world.etu")), vec![
+            tokenize!(Keyword Do),
+            tokenize!(Keyword Entry),
+            crate::TokenType::Operators("-".into()),
+            Indent,
+            tokenize!(Ident "log"),
+            crate::TokenType::Operators("<-".into()),
+            crate::TokenType::String("Hello, World".into()),
+            Terminator,
+            Dedent,
+            tokenize!(EOF),
+        ]);
+
+        assert_eq!(indented(include_str!("../../hello_alt.etu"))[2..5], [
+            tokenize!(Delimit ',' (Open 0)),
+            tokenize!(Delimit '-' (Close 0)),
+            Indent,
+        ]);
+
+        assert_eq!(indented("a\n  b\n\n    c // note\n  d.\ne"), vec![
+            tokenize!(Ident "a"), Indent,
+            tokenize!(Ident "b"), Indent,
+            tokenize!(Ident "c"), Terminator, Dedent,
+            tokenize!(Ident "d"), Terminator, Dedent,
+            tokenize!(Ident "e"), Terminator,
+            tokenize!(EOF),
+        ]);
+
+        // brackets carry a statement over onto the next line
+        assert_eq!(indented("x <- (1 +\n        2)\ny").len(), 11);
+    }
+
+    #[test]
+    fn inconsistent_indentation() {
+        let config = crate::LexerConfig::default().with_indentation(true);
+
+        for (src, line) in [("a\n    b\n  c", 3), ("a\n\tb\n\t  c", 3), ("a\n\tb\n  c", 3)] {
+            let mut lexer = crate::Lexer::with_config(src, config.clone());
+            match lexer.find_map(Result::err) {
+                Some(crate::LexerError::InconsistentIndentation { span, .. }) => {
+                    assert_eq!((span.start.line, span.start.col), (line, 1), "{:?}", src);
+                },
+                other => panic!("expected inconsistent indentation in {:?}, got {:?}", src, other),
+            }
+        }
+
+        let mut lexer = crate::Lexer::with_config("a\n    b\n  c\n  d", config);
+        lexer.set_recovery(true);
+        assert!(lexer.by_ref().all(|token| token.is_ok()));
+        assert_eq!(lexer.diagnostics().len(), 1);
+    }
+
+    #[test]
+    fn dialect_detection() {
+        use crate::Dialect;
+        use std::path::Path;
+
+        assert_eq!(Dialect::detect(Some(Path::new("math.etu")), ""), Dialect::Etu);
+        assert_eq!(Dialect::detect(Some(Path::new("function.idi")), ""), Dialect::Idi);
+        assert_eq!(Dialect::detect(Some(Path::new("function.idi")), "\n//! dialect: etu\ndo entry -"), Dialect::Etu);
+        assert_eq!(Dialect::detect(None, "do entry -"), Dialect::Idi);
+    }
+
+    #[test]
+    fn dialects_parse_alike() {
+        use crate::{Dialect, Expr, Statement};
+
+        let idi = crate::parse(include_str!("../idiom_files/compare.idi"), Dialect::Idi).unwrap();
+        let etu = crate::parse(include_str!("../idiom_files/compare.etu"), Dialect::Etu).unwrap();
+        assert_eq!(idi, etu);
+
+        let functions = idi.items.iter().filter_map(|item| match item {
+            crate::Item::Function(function) => Some(function),
+            _ => None,
+        }).collect::<Vec<_>>();
+
+        let add = functions[0];
+        assert_eq!((add.docs.clone(), add.name.as_str(), add.params.clone()), (vec!["Adds two numbers".to_string()], "add", vec!["a".to_string(), "b".to_string()]));
+
+        let main = functions[1];
+        assert_eq!(main.body.len(), 4);
+        match &main.body[1] {
+            Statement::When(branches) => {
+                assert_eq!(branches.len(), 3);
+                assert!(branches[2].condition.is_none());
+                assert_eq!(branches[0].body, vec![Statement::Expr(Expr::Binary {
+                    op: "->".to_string(),
+                    lhs: Box::new(Expr::Call { name: "add".to_string(), args: vec![Expr::Identifier("a".to_string()), Expr::Identifier("b".to_string())] }),
+                    rhs: Box::new(Expr::Identifier("value".to_string())),
+                })]);
+            },
+            other => panic!("expected when, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sample_programs_parse() {
+        use crate::Dialect;
+
+        for (src, dialect, functions) in [
+            (include_str!("../idiom_files/function.idi"), Dialect::Idi, 6),
+            (include_str!("../../math.etu"), Dialect::Etu, 2),
+            (include_str!("../../hello_world.etu"), Dialect::Etu, 1),
+            (include_str!("../../hello_alt.etu"), Dialect::Etu, 1),
+        ] {
+            let items = crate::parse(src, dialect).unwrap().items;
+            assert_eq!(items.iter().filter(|item| matches!(item, crate::Item::Function(_))).count(), functions);
+        }
+
+        match crate::parse("go f, a -\n| a b ~", Dialect::Idi) {
+            Err(crate::LexerError::ImproperUsage { expected: crate::TokenType::Terminator, found, .. }) => {
+                assert_eq!(found.kind, tokenize!(Ident "b"));
+            },
+            other => panic!("expected a missing terminator, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stray_doc_comments() {
+        use crate::{Dialect, Item, Statement};
+
+        // with no function after them, doc comments are kept as comments
+        let items = crate::parse("/// lost\n.\ngo f, -\n| x. ~\n/// note\n", Dialect::Idi).unwrap().items;
+        assert_eq!(items[0], Item::Comment("/// lost".to_string()));
+        assert!(matches!(&items[1], Item::Function(function) if function.docs.is_empty()));
+        assert_eq!(items.last(), Some(&Item::Comment("/// note".to_string())));
+
+        // as they are inside bodies and branches
+        let src = "go f, -\n|\n    /// inner\n    x.\n    when, x -\n    |\n        ///  branch\n        ^ x.\n    ~\n~\n/// note\n";
+        let items = crate::parse(src, Dialect::Idi).unwrap().items;
+        match &items[0] {
+            Item::Function(function) => {
+                assert_eq!(function.body[0], Statement::Comment("/// inner".to_string()));
+                assert!(matches!(&function.body[2], Statement::When(branches) if branches[0].body[0] == Statement::Comment("///  branch".to_string())));
+            },
+            other => panic!("expected a function, got {:?}", other),
+        }
+
+        assert_eq!(crate::translate(src, Dialect::Idi, Dialect::Idi).unwrap().text, src);
+        let etu = crate::translate(src, Dialect::Idi, Dialect::Etu).unwrap().text;
+        assert_eq!(etu, "do f -\n  /// inner\n  x\n  when: x -\n    ///  branch\n    ^ x\n/// note\n");
+        assert_eq!(crate::translate(&etu, Dialect::Etu, Dialect::Idi).unwrap().text, src);
+    }
+
+    #[test]
+    fn deeply_nested_input() {
+        use crate::Dialect;
+
+        let nest = |open: &str, inner: &str, close: &str, depth: usize| format!("go f, -\n| {}{}{} ~\n", open.repeat(depth), inner, close.repeat(depth));
+        let brackets = |depth| nest("x <- (", "1", ")", depth);
+        let calls = |depth| nest("g, ", "1", " -", depth);
+        let whens = |depth| nest("when, x - | ", "x.", " ~", depth);
+
+        for src in [brackets(30), calls(60), whens(60), nest("x <- ", "1", "", 60)] {
+            assert!(crate::parse(&src, Dialect::Idi).is_ok(), "{}", &src[..40]);
+        }
+
+        // past the limit the parse fails where it gave up, rather than overflowing the stack
+        for src in [brackets(10_000), calls(10_000), whens(10_000), nest("x <- ", "1", "", 10_000)] {
+            match crate::parse(&src, Dialect::Idi) {
+                Err(crate::LexerError::NestedTooDeeply { limit, span }) => {
+                    assert_eq!(limit, crate::MAX_DEPTH);
+                    assert!(span.start.offset > crate::MAX_DEPTH && span.start.offset < src.len() / 2);
+                },
+                other => panic!("expected nesting too deep, got {:?}", other.map(|_| ())),
+            }
+        }
+        assert!(crate::translate(&brackets(10_000), Dialect::Idi, Dialect::Etu).is_err());
+    }
+
+    #[test]
+    fn translation_round_trips() {
+        use crate::Dialect::{Etu, Idi};
+
+        for (src, from, to) in [
+            (include_str!("../idiom_files/function.idi"), Idi, Etu),
+            (include_str!("../idiom_files/compare.idi"), Idi, Etu),
+            (include_str!("../idiom_files/compare.etu"), Etu, Idi),
+            (include_str!("../../hello_world.etu"), Etu, Idi),
+            (include_str!("../../hello_alt.etu"), Etu, Idi),
+        ] {
+            let translation = crate::translate(src, from, to).unwrap();
+            assert!(translation.unsupported.is_empty());
+            assert_eq!(crate::parse(&translation.text, to).unwrap(), crate::parse(src, from).unwrap());
+
+            // and written back out the same way every time
+            assert_eq!(crate::translate(&translation.text, to, to).unwrap().text, translation.text);
+        }
+
+        assert_eq!(crate::translate(include_str!("../../hello_world.etu"), Etu, Idi).unwrap().text,
+            "go entry, -\n|\n    log <- \"Hello, World\".\n~\n");
+
+        // statements ending in a fraction, which '.' then follows in .idi
+        let etu = "do entry -\n  x <- 0.5\n  z <- 3.0f32\n  ^ .25\n";
+        let idi = crate::translate(etu, Etu, Idi).unwrap().text;
+        assert_eq!(idi, "go entry, -\n|\n    x <- 0.5.\n    z <- 3.0f32.\n    ^ .25.\n~\n");
+        assert_eq!(crate::parse(&idi, Idi).unwrap(), crate::parse(etu, Etu).unwrap());
+        assert_eq!(crate::translate(&idi, Idi, Etu).unwrap().text, etu);
+    }
+
+    #[test]
+    fn translation_keeps_comments() {
+        let src = "//! dialect: etu\n// greeting\ndo entry -\n  /* say it */\n  log <- \"hi\" // loudly\n  ^ 0\n";
+        let translation = crate::translate(src, crate::Dialect::Etu, crate::Dialect::Idi).unwrap();
+
+        assert_eq!(translation.text, "//! dialect: idi\n// greeting\ngo entry, -\n|\n    /* say it */\n    log <- \"hi\". // loudly\n    ^ 0.\n~\n");
+        assert_eq!(crate::Dialect::detect(None, &translation.text), crate::Dialect::Idi);
+
+        use crate::Dialect::{Etu, Idi};
+
+        // a statement is written on one line, so comments within it go ahead of it
+        let src = "go f, a -\n|\n    x <- (a +\n        // mid\n        1).\n    y.\n~\n";
+        assert_eq!(crate::translate(src, Idi, Idi).unwrap().text, "go f, a -\n|\n    // mid\n    x <- (a + 1).\n    y.\n~\n");
+        assert_eq!(crate::translate(src, Idi, Etu).unwrap().text, "do f: a -\n  // mid\n  x <- (a + 1)\n  y\n");
+
+        // what sits between two branches stays between them
+        let src = "go f, a -\n|\n    when, a -\n    |\n        x.\n    ~ // after when\n\n    // before else\n    or else\n    |\n        y.\n    ~\n~\n";
+        assert_eq!(crate::translate(src, Idi, Idi).unwrap().text, src);
+        let etu = crate::translate(src, Idi, Etu).unwrap().text;
+        assert_eq!(etu, "do f: a -\n  when: a -\n    x // after when\n\n  // before else\n  or else\n    y\n");
+        assert_eq!(crate::translate(&etu, Etu, Etu).unwrap().text, etu);
+    }
+
+    #[test]
+    fn translation_keeps_layout() {
+        use crate::Dialect::{Etu, Idi};
+
+        let src = include_str!("../idiom_files/layout.idi");
+        assert_eq!(crate::translate(src, Idi, Idi).unwrap().text, src);
+
+        let etu = crate::translate(src, Idi, Etu).unwrap().text;
+        assert!(etu.contains("do add: a, b - // header note\n  ^ (a + b) // the sum\n\n\ndo main -"));
+        assert!(etu.contains("    value <- (add: a, b) /* added */\n  or else\n"));
+        assert_eq!(crate::translate(&etu, Etu, Etu).unwrap().text, etu);
+        assert_eq!(crate::translate(&etu, Etu, Idi).unwrap().text, src);
+
+        // with no '~' to end on, a comment trailing one moves to a line of its own
+        let translation = crate::translate("go f, -\n| x. // a\n~ // b\n", Idi, Etu).unwrap();
+        assert_eq!(translation.text, "do f -\n  x // a\n// b\n");
+    }
+
+    #[test]
+    fn translation_reports_the_untranslatable() {
+        let translation = crate::translate(include_str!("../../math.etu"), crate::Dialect::Etu, crate::Dialect::Idi).unwrap();
+        assert_eq!(translation.unsupported, vec!["a ',' separated list in 'entry' has no .idi equivalent".to_string()]);
+
+        let translation = crate::translate("go nothing, -\n| // todo\n~", crate::Dialect::Idi, crate::Dialect::Etu).unwrap();
+        assert_eq!(translation.text, "do nothing - // todo\n");
+        assert_eq!(translation.unsupported, vec!["an empty body in 'nothing' has no .etu equivalent".to_string()]);
+    }
+
+    #[test]
+    fn lossless_trivia() {
+        use crate::TriviaKind::*;
+
+        let mut lexer = crate::Lexer::new("  x <- 1 \r\n\n\ty.");
+        lexer.set_lossless(true);
+        let tokens = lexer.collect::<Result<Vec<_>, _>>().unwrap();
+        let trivia = |trivia: &Vec<crate::Trivia>| trivia.iter().map(|t| (t.kind, t.text.to_string())).collect::<Vec<_>>();
+
+        assert_eq!(trivia(&tokens[0].leading), vec![(Whitespace, "  ".to_string())]);
+        assert_eq!(trivia(&tokens[0].trailing), vec![(Whitespace, " ".to_string())]);
+        assert_eq!(trivia(&tokens[2].trailing), vec![(Whitespace, " ".to_string()), (Newline, "\r\n".to_string())]);
+        assert_eq!(trivia(&tokens[3].leading), vec![(Newline, "\n".to_string()), (Whitespace, "\t".to_string())]);
+        assert_eq!(tokens[3].leading[1].span.start.line, 3);
+        assert!(tokens[4].trailing.is_empty());
+        assert_eq!(tokens[5].kind, tokenize!(EOF));
+
+        // lossless lexing recovers while it is on, and leaves the recovery setting as it was
+        let mut lexer = crate::Lexer::new("$ x");
+        lexer.set_lossless(true);
+        assert!(lexer.next_token().is_ok());
+
+        let mut lexer = crate::Lexer::new("$ x");
+        lexer.set_lossless(true);
+        lexer.set_lossless(false);
+        assert!(lexer.next_token().is_err());
+    }
+
+    #[test]
+    fn borrowed_tokens() {
+        use std::borrow::Cow;
+
+        let src = "name \"plain\" \"tab\\t\" 16bFFu8 // note";
+        let mut lexer = crate::Lexer::new(src);
+        lexer.set_lossless(true);
+        let tokens = lexer.collect::<Result<Vec<_>, _>>().unwrap();
+
+        // only the escaped string had to be copied out of the source
+        assert!(matches!(&tokens[0].kind, crate::TokenType::Identifiers(Cow::Borrowed("name"))));
+        assert!(matches!(&tokens[1].kind, crate::TokenType::String(Cow::Borrowed("plain"))));
+        assert!(matches!(&tokens[2].kind, crate::TokenType::String(Cow::Owned(text)) if text == "tab\t"));
+        assert!(matches!(&tokens[3].kind, crate::TokenType::Numeric{ raw: Cow::Borrowed("FF"), .. }));
+        assert!(matches!(&tokens[3].trailing[1].text, Cow::Borrowed("// note")));
+
+        let owned = tokens[0].clone().into_owned();
+        assert_eq!(owned, tokens[0]);
+    }
+
+    // Hands out at most `chunk` bytes a read, and fails once they are gone if `fail` is set
+    struct Trickle<'s> {
+        bytes: &'s [u8],
+        chunk: usize,
+        fail: bool,
+    }
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.bytes.is_empty() && self.fail {
+                return Err(std::io::Error::other("disconnected"));
+            }
+            let read = self.chunk.min(buf.len()).min(self.bytes.len());
+            buf[..read].copy_from_slice(&self.bytes[..read]);
+            self.bytes = &self.bytes[read..];
+            Ok(read)
+        }
+    }
+
+    // Everything a lexer over the whole of `src` hands out, written out to compare
+    fn lex_whole(src: &str, config: crate::LexerConfig, recovering: bool, lossless: bool) -> String {
+        let mut lexer = crate::Lexer::with_config(src, config);
+        lexer.set_recovery(recovering);
+        lexer.set_lossless(lossless);
+        let tokens = lexer.by_ref().map(|token| format!("{:?}", token)).collect::<Vec<_>>();
+        format!("{:?} {:?}", tokens, lexer.diagnostics())
+    }
+
+    // `src` lexed as a whole and from a reader trickling it in `chunk` bytes at a time
+    fn read_alike(src: &str, config: crate::LexerConfig, chunk: usize, recovering: bool, lossless: bool) -> (String, String) {
+        let whole = lex_whole(src, config.clone(), recovering, lossless);
+        let mut reader = crate::ReadLexer::with_config(Trickle { bytes: src.as_bytes(), chunk, fail: false }, config);
+        reader.set_recovery(recovering);
+        reader.set_lossless(lossless);
+        let tokens = reader.by_ref().map(|token| format!("{:?}", token)).collect::<Vec<_>>();
+        (whole, format!("{:?} {:?}", tokens, reader.diagnostics()))
+    }
+
+    #[test]
+    fn read_lexer_matches_lexer() {
+        let idi = crate::LexerConfig::default();
+        let etu = crate::Dialect::Etu.config();
+
+        for (src, config) in [
+            (include_str!("../idiom_files/function.idi"), &idi),
+            (include_str!("../idiom_files/comments.idi"), &idi),
+            (include_str!("../idiom_files/numbers.idi"), &idi),
+            (include_str!("../idiom_files/strings.idi"), &idi),
+            (include_str!("../idiom_files/compare.etu"), &etu),
+            (include_str!("../../math.etu"), &etu),
+            ("x <- \"é😀\\n\" /* a /* b */ c */ 'ü' --> f, 1 - 2 -\r\n( [ ) ] 48b1", &idi),
+            // errors spanning lines, after which indentation is measured from the line they end on
+            ("f x\n  y <- 'a\nb' z\n  w\nv\n", &etu),
+            ("f x\n  y <- \"\\q\n  b\" z\n  w\nv\n", &etu),
+            ("f x\n  y <- \"\\q\nb\"\n  w\nv\n", &etu),
+            ("f x\n  y /* a\n  b\nc\n", &etu),
+        ] {
+            for chunk in [1, 2, 3, 7, crate::READ_CHUNK] {
+                for (recovering, lossless) in [(false, false), (true, false), (true, true)] {
+                    let (whole, read) = read_alike(src, config.clone(), chunk, recovering, lossless);
+                    assert_eq!(whole, read, "{:?} read {} bytes at a time", src, chunk);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn read_lexer_errors() {
+        use std::io::ErrorKind;
+
+        // characters cut in two by a read are put back together
+        let mut reader = crate::ReadLexer::new(Trickle { bytes: "\"é😀\"".as_bytes(), chunk: 1, fail: false });
+        assert_eq!(reader.next_token().unwrap().kind, crate::TokenType::String("é😀".into()));
+
+        let io_error = |bytes: &[u8], fail: bool| match crate::ReadLexer::new(Trickle { bytes, chunk: 4, fail }).last() {
+            Some(Err(crate::LexerError::FileIO { source, span })) => (source.kind(), span.start),
+            other => panic!("expected an I/O error, got {:?}", other),
+        };
+        assert_eq!(io_error(b"a \xffb", false), (ErrorKind::InvalidData, crate::Position { offset: 2, line: 1, col: 3 }));
+        assert_eq!(io_error(b"a\n\xc3", false), (ErrorKind::UnexpectedEof, crate::Position { offset: 2, line: 2, col: 1 }));
+        assert_eq!(io_error(b"go f, a -\n| a. ~", true), (ErrorKind::Other, crate::Position { offset: 16, line: 2, col: 7 }));
+
+        // tokens settled before the reader gave out are still handed over
+        for chunk in [1, 4] {
+            let tokens = crate::ReadLexer::new(Trickle { bytes: b"go f, a -\n| a. ~", chunk, fail: true }).collect::<Vec<_>>();
+            assert!(tokens.len() > 2 && tokens[..tokens.len() - 1].iter().all(Result::is_ok), "{} bytes at a time", chunk);
+            assert!(matches!(tokens.last(), Some(Err(crate::LexerError::FileIO { .. }))));
+        }
+    }
+
+    #[test]
+    fn read_lexer_long_tokens() {
+        let string = format!("x <- \"{}\". y", "é".repeat(128 * 1024));
+        let comment = format!("/* {} */ y", "a\n".repeat(128 * 1024));
+        for src in [&string, &comment] {
+            let (whole, read) = read_alike(src, crate::LexerConfig::default(), 64, true, false);
+            assert!(whole == read, "a token {} bytes long read 64 bytes at a time", src.len());
+        }
+    }
+
+    #[test]
+    fn read_lexer_bounded_memory() {
+        let src = "dec x. x <- (x + 1) * 2. // again\n".repeat(50_000);
+        let mut reader = crate::ReadLexer::new(src.as_bytes());
+
+        let mut tokens = 0;
+        while reader.next_token().unwrap().kind != crate::TokenType::EOF {
+            assert!(reader.buffered() <= 2 * crate::READ_CHUNK);
+            tokens += 1;
+        }
+        assert_eq!(tokens, crate::Lexer::new(&src).count() - 1);
+    }
+
+    // Applies each edit in turn, comparing the document with lexing its new text afresh
+    fn edits_alike(src: &str, config: crate::LexerConfig, recovering: bool, lossless: bool, edits: &[(std::ops::Range<usize>, &str)]) {
+        let mut document = crate::LexedDocument::with_config(src, config.clone());
+        document.set_recovery(recovering);
+        document.set_lossless(lossless);
+
+        for (range, replacement) in edits {
+            let before = document.text().to_string();
+            document.edit(range.clone(), replacement).unwrap();
+
+            let tokens = document.tokens().map(|token| format!("{:?}", token)).collect::<Vec<_>>();
+            let diagnostics = document.diagnostics().collect::<Vec<_>>();
+            assert_eq!(
+                format!("{:?} {:?}", tokens, diagnostics),
+                lex_whole(document.text(), config.clone(), recovering, lossless),
+                "{:?} with {:?} replaced by {:?}", before, range, replacement,
+            );
+        }
+    }
+
+    #[test]
+    fn document_edits_match_relexing() {
+        let idi = crate::LexerConfig::default();
+        let etu = crate::Dialect::Etu.config();
+        let function = include_str!("../idiom_files/function.idi");
+        let compare = include_str!("../idiom_files/compare.etu");
+        let mixed = "x <- \"é😀\\n\" /* a /* b */ c */ 'ü' --> f, 1 - 2 -\r\n( [ ) ] 48b1\n| y. ~\n";
+
+        // typing, deleting, and edits that open or close strings, comments and delimiters
+        let edits = |src: &str| {
+            let middle = src.char_indices().nth(src.chars().count() / 2).map_or(0, |(at, _)| at);
+            let end = src.len();
+            vec![
+                (0..0, "go"), (0..2, ""), (middle..middle, "x"), (middle..middle + 1, ""),
+                (middle..middle, "\""), (middle..middle + 1, ""), (middle..middle, "/*"), (end..end, "*/"),
+                (middle..middle, "\n    "), (middle..middle + 5, ""), (middle..middle, "("), (0..0, ")"),
+                (0..1, ""), (middle..middle + 1, ""), (middle..middle, " - "), (0..end.min(8), "\t| a\n"),
+            ]
+        };
+
+        for (src, config) in [(function, &idi), (mixed, &idi), (include_str!("../idiom_files/strings.idi"), &idi), (compare, &etu)] {
+            for recovering in [false, true] {
+                for lossless in [false, true] {
+                    edits_alike(src, config.clone(), recovering, lossless, &edits(src));
+                }
+            }
+        }
+
+        // tokens that ran into the end of the text change with anything added after them
+        edits_alike("log <- \"first line\n", idi.clone(), true, true, &[(19..19, "second\" .")]);
+        edits_alike("/* a\n", idi.clone(), true, false, &[(5..5, "*/ b")]);
+        edits_alike("[\n", idi.clone(), false, false, &[(2..2, ">")]);
+        edits_alike("f x\n  [\n", etu.clone(), false, false, &[(8..8, ">\n  y")]);
+    }
+
+    #[test]
+    fn document_relexes_locally() {
+        let line = "dec x. x <- (x + 1) * 2. // again\n";
+        let src = format!("go f, a -\n|\n{}~\n", line.repeat(1000));
+        let mut document = crate::LexedDocument::new(src.as_str());
+        let tokens = document.tokens().count();
+
+        // a change within a line lexes that line and little else
+        let at = src.len() / 2;
+        let at = at + src[at..].find("dec x").unwrap() + 4;
+        let relexed = document.edit(at..at + 1, "count").unwrap();
+        assert!(relexed.len() < 30, "{:?}", relexed);
+        assert_eq!(document.tokens().count(), tokens);
+        assert!(document.text().contains("dec count. x"));
+
+        // as does breaking it in two, moving every line after it down
+        let relexed = document.edit(at..at, "\n").unwrap();
+        assert!(relexed.len() < 30, "{:?}", relexed);
+        assert_eq!(document.tokens().last().unwrap().as_ref().unwrap().span.start.line, 1005);
+
+        // opening a comment swallows the rest, however far it runs
+        let relexed = document.edit(at..at, "/*").unwrap();
+        assert_eq!(relexed.end, document.tokens().count());
+
+        // ranges off character boundaries are refused and change nothing
+        let mut document = crate::LexedDocument::new("'é'");
+        assert_eq!(document.edit(2..3, ""), None);
+        assert_eq!(document.edit(0..9, ""), None);
+        assert_eq!(document.text(), "'é'");
+    }
+
+    #[test]
+    fn lexing_resumes_from_snapshots() {
+        let src = include_str!("../idiom_files/function.idi");
+        let mut document = crate::LexedDocument::new(src);
+        document.set_recovery(true);
+        let tokens = document.tokens().map(|token| format!("{:?}", token)).collect::<Vec<_>>();
+
+        for (index, state) in document.snapshots() {
+            let mut lexer = crate::Lexer::resume(src, 0, crate::LexerConfig::default(), state.clone());
+            let rest = lexer.by_ref().map(|token| format!("{:?}", token)).collect::<Vec<_>>();
+            assert_eq!(rest, tokens[index..], "resumed at {:?}", state.position());
+        }
+    }
+
+    // Lexes to the end in both modes, evaluating numbers and slicing every span on the way
+    fn lex_everything(src: &str) -> Result<(), String> {
+        let bound = src.chars().count() * 2 + 2;
+
+        let mut lexer = crate::Lexer::new(src);
+        for _ in 0..bound {
+            match lexer.next_token() {
+                Ok(crate::Token { kind: crate::TokenType::EOF, .. }) => break,
+                Ok(token) => { let _ = crate::evaluate(&token); },
+                Err(error) => { let _ = lexer.slice(error.span()); },
+            }
+        }
+
+        let mut lexer = crate::Lexer::new(src);
+        lexer.set_recovery(true);
+        for _ in 0..bound {
+            match lexer.next_token() {
+                Ok(crate::Token { kind: crate::TokenType::EOF, .. }) => return Ok(()),
+                Ok(token) => {
+                    let _ = lexer.slice(token.span);
+                    if let Ok(evaluated) = crate::evaluate(&token) {
+                        let _ = evaluated.value.to_literal(&crate::NumericBase::Decimal);
+                    }
+                },
+                Err(error) => return Err(format!("recovery returned {:?}", error)),
+            }
+        }
+        Err(format!("no EOF after {} tokens", bound))
+    }
+
+    fn parse_everything(src: &str) {
+        let _ = crate::parse(src, crate::Dialect::Idi);
+        let _ = crate::parse(src, crate::Dialect::Etu);
+    }
+
+    #[test]
+    fn panic_free_edge_cases() {
+        for src in [
+            "", "-", "-->", "~", ">", ",", "'", "\"", "'\\", "\"\\u{", "'\\u{110000}'",
+            "1e9223372036854775807", "1.5e-9223372036854775808", "1e99999999999999999999",
+            "4294967296b1", "0b", "64b", "64b.", "36bz_", "1__0", "1u", "1u999", "1.5i8", "0.", ".0.",
+            "| , a - { [ ( < ", "( [ ) ] } > - ~", "\u{0}\u{feff}é😀",
+        ] {
+            assert_eq!(lex_everything(src), Ok(()), "{:?}", src);
+        }
+    }
+
+    // Text made of the pieces programs are, so indentation, strings and comments turn up often
+    const PROGRAM_TEXT: &str = "([a-z0-9 .,:|~()<>+*/^?'\"\\\n\t-]|  |\n  |\"\\\\q\n|do |when |// |/\\* |\\*/|-->){0,32}";
+
+    proptest::proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(2000))]
+
+        #[test]
+        fn arbitrary_utf8_never_panics(src in "\\PC*") {
+            parse_everything(&src);
+            proptest::prop_assert_eq!(lex_everything(&src), Ok(()));
+        }
+
+        #[test]
+        fn lossless_reproduces_arbitrary_utf8(src in "\\PC*") {
+            proptest::prop_assert_eq!(rebuild(&src, false), src.clone());
+            proptest::prop_assert_eq!(rebuild(&src, true), src);
+        }
+
+        #[test]
+        fn reads_arbitrary_utf8_alike(src in "\\PC*", chunk in 1usize..16) {
+            let (whole, read) = read_alike(&src, crate::LexerConfig::default(), chunk, true, true);
+            proptest::prop_assert_eq!(whole, read);
+        }
+
+        #[test]
+        fn reads_program_text_alike(src in PROGRAM_TEXT, chunk in 1usize..16, etu: bool, recovering: bool, lossless: bool) {
+            let config = if etu { crate::Dialect::Etu.config() } else { crate::LexerConfig::default() };
+            let (whole, read) = read_alike(&src, config, chunk, recovering, lossless);
+            proptest::prop_assert_eq!(whole, read);
+        }
+
+        #[test]
+        fn edits_arbitrary_utf8_alike(src in "\\PC*", at in 0usize..64, len in 0usize..8, replacement in "\\PC{0,6}") {
+            let chars = src.char_indices().map(|(offset, _)| offset).chain([src.len()]).collect::<Vec<_>>();
+            let start = chars[at % chars.len()];
+            let end = chars[(at % chars.len() + len).min(chars.len() - 1)];
+            edits_alike(&src, crate::LexerConfig::default(), true, true, &[(start..end, &replacement), (0..0, "\n")]);
+        }
+
+        #[test]
+        fn edits_program_text_alike(src in PROGRAM_TEXT, at in 0usize..128, len in 0usize..8, replacement in PROGRAM_TEXT, etu: bool, recovering: bool, lossless: bool) {
+            let config = if etu { crate::Dialect::Etu.config() } else { crate::LexerConfig::default() };
+            let chars = src.char_indices().map(|(offset, _)| offset).chain([src.len()]).collect::<Vec<_>>();
+            let start = chars[at % chars.len()];
+            let end = chars[(at % chars.len() + len).min(chars.len() - 1)];
+            let appended = src.len() - (end - start) + replacement.len();
+            edits_alike(&src, config, recovering, lossless, &[(start..end, &replacement), (appended..appended, &replacement)]);
+        }
+
+        #[test]
+        fn lexer_alphabet_never_panics(src in "([0-9a-fA-FbBeE_.,:|~{}()<>+*/^?'\" \n\t\\[\\]\\\\-]|true|false|go|64b|16b|36b){0,48}") {
+            parse_everything(&src);
+            proptest::prop_assert_eq!(lex_everything(&src), Ok(()));
+        }
+    }
 }